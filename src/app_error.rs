@@ -1,7 +1,8 @@
-use actix_web::{HttpResponse, ResponseError, http::StatusCode};
+use actix_web::{HttpResponse, ResponseError, http::{StatusCode, header::RETRY_AFTER}};
 use thiserror::Error;
 use serde::Serialize;
 use actix_multipart::MultipartError;
+use mongodb::error::{ErrorKind, WriteFailure};
 
 #[derive(Serialize)]
 struct ErrorBody {
@@ -43,8 +44,17 @@ pub enum AppError {
     #[error("Internal Server Error")]
     ReqwestError(#[from] reqwest::Error),
 
-    #[error("Strip server unresponsive")]
-    StripeError
+    #[error("{0}")]
+    StripeError(String),
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error("Too many attempts, try again in {0} seconds")]
+    TooManyRequests(u64),
+
+    #[error("Only {0} left in stock")]
+    OutOfStock(i32)
 }
 
 impl ResponseError for AppError {
@@ -59,7 +69,10 @@ impl ResponseError for AppError {
             AppError::JsonDeserializationError(_) => StatusCode::BAD_REQUEST,
             AppError::MultipartError(_) => StatusCode::BAD_REQUEST,
             AppError::ReqwestError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            AppError::StripeError => StatusCode::INTERNAL_SERVER_ERROR
+            AppError::StripeError(_) => StatusCode::BAD_GATEWAY,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::OutOfStock(_) => StatusCode::CONFLICT
         }
     }
 
@@ -71,7 +84,11 @@ impl ResponseError for AppError {
             }
         };
 
-        HttpResponse::build(self.status_code()).json(body)
+        let mut response = HttpResponse::build(self.status_code());
+        if let AppError::TooManyRequests(secs) = self {
+            response.insert_header((RETRY_AFTER, secs.to_string()));
+        }
+        response.json(body)
     }
 }
 
@@ -87,4 +104,62 @@ impl AppError {
     pub fn forbidden(msg: &str) -> Self {
         AppError::Forbidden(msg.to_owned())
     }
+
+    pub fn conflict(msg: &str) -> Self {
+        AppError::Conflict(msg.to_owned())
+    }
+
+    /// Turns a unique index violation into a conflict with the message, and
+    /// any other database error into `AppError::Database`.
+    pub fn from_write(e: mongodb::error::Error, msg: &str) -> Self {
+        match is_duplicate_key(&e) {
+            true => AppError::conflict(msg),
+            false => AppError::Database(e)
+        }
+    }
+}
+
+fn is_duplicate_key(e: &mongodb::error::Error) -> bool {
+    const DUPLICATE_KEY: i32 = 11000;
+
+    match *e.kind {
+        ErrorKind::Write(WriteFailure::WriteError(ref w)) => w.code == DUPLICATE_KEY,
+        ErrorKind::Command(ref c) => c.code == DUPLICATE_KEY,
+        ErrorKind::InsertMany(ref i) => i.write_errors.iter().flatten().any(|w| w.code == DUPLICATE_KEY),
+        _ => false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //status_code
+    #[test]
+    fn conflict_is_409() {
+        let result = AppError::conflict("Duplicate");
+        assert_eq!(result.status_code(), StatusCode::CONFLICT);
+    }
+
+    #[test]
+    fn out_of_stock_is_409_with_available() {
+        let result = AppError::OutOfStock(3);
+        assert_eq!(result.status_code(), StatusCode::CONFLICT);
+        assert_eq!(result.to_string(), "Only 3 left in stock");
+    }
+
+    #[test]
+    fn stripe_error_is_502_with_message() {
+        let result = AppError::StripeError(String::from("Stripe is busy, try again shortly"));
+        assert_eq!(result.status_code(), StatusCode::BAD_GATEWAY);
+        assert_eq!(result.to_string(), "Stripe is busy, try again shortly");
+    }
+
+    //from_write
+    #[test]
+    fn other_errors_stay_database_errors() {
+        let e = mongodb::error::Error::custom("Connection lost");
+        let result = AppError::from_write(e, "Duplicate");
+        assert!(matches!(result, AppError::Database(_)));
+    }
 }
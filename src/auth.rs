@@ -1,20 +1,226 @@
-use mongodb::{Database, bson::oid::ObjectId};
-use actix_web::HttpRequest;
+use mongodb::Database;
+use actix_web::{HttpRequest, http::header::AUTHORIZATION};
 
 use crate::app_error::AppError;
-use crate::models::vendor::Vendor;
+use crate::helpers::{client_ip, hash_token};
+use crate::models::{
+    vendor::Vendor,
+    admin::Admin,
+    session::{Session, SessionKind},
+    api_key::{ApiKey, ApiScope},
+    staff::{Staff, Role},
+    customer::Customer,
+    audit::{Actor, ActorKind}
+};
+
+/// The vendor a request acts for, and the staff member acting if it isn't
+/// the vendor's owner.
+pub struct VendorUser {
+    pub vendor: Vendor,
+    pub staff: Option<Staff>
+}
+
+impl VendorUser {
+    pub fn role(&self) -> Role {
+        self.staff.as_ref().map(|s| s.role).unwrap_or(Role::Owner)
+    }
+
+    pub fn actor(&self) -> Actor {
+        match &self.staff {
+            Some(s) => Actor::new(ActorKind::Staff, s._id),
+            None => Actor::new(ActorKind::Vendor, self.vendor._id)
+        }
+    }
+
+    pub fn require(&self, role: Role) -> Result<(), AppError> {
+        match self.role() >= role {
+            true => Ok(()),
+            false => Err(AppError::forbidden("Your role does not allow this action"))
+        }
+    }
+}
 
 pub async fn vendor_auth(
     db: &Database,
     req: &HttpRequest
 ) -> Result<Vendor, AppError> {
+    let (vendor, _) = vendor_session_auth(db, req).await?;
+    Ok(vendor)
+}
+
+/// Authenticates with an API key from the Authorization header if there is
+/// one, otherwise with the session cookie. Keys must have the scope.
+pub async fn vendor_scoped_auth(
+    db: &Database,
+    req: &HttpRequest,
+    scope: ApiScope
+) -> Result<(Vendor, Actor), AppError> {
+    let raw = match bearer_token(req) {
+        Some(r) => r,
+        None => {
+            let user = vendor_role_auth(db, req, Role::Inventory).await?;
+            let actor = user.actor();
+            return Ok((user.vendor, actor));
+        }
+    };
+
+    let key = ApiKey::find_by_key(db, raw).await?;
+    key.ensure_scope(scope)?;
+    let vendor = match Vendor::find_by_id(db, key.vendor).await {
+        Ok(v) => v,
+        Err(AppError::NotFound(_)) => return Err(AppError::Auth),
+        Err(e) => return Err(e)
+    };
+    if vendor.pass_hash.is_none() {
+        return Err(AppError::forbidden("Vendor password not set"));
+    }
+    vendor.ensure_active()?;
+    key.mark_used(db).await?;
+    Ok((vendor, Actor::new(ActorKind::ApiKey, key._id)))
+}
+
+/// Owner only. Staff get `AppError::Forbidden`.
+pub async fn vendor_session_auth(
+    db: &Database,
+    req: &HttpRequest
+) -> Result<(Vendor, Session), AppError> {
+    let (user, session) = vendor_user_session_auth(db, req).await?;
+    user.require(Role::Owner)?;
+    Ok((user.vendor, session))
+}
+
+pub async fn vendor_role_auth(
+    db: &Database,
+    req: &HttpRequest,
+    role: Role
+) -> Result<VendorUser, AppError> {
+    let (user, _) = vendor_user_session_auth(db, req).await?;
+    user.require(role)?;
+    Ok(user)
+}
+
+/// Accepts the session of the vendor's owner or any of its staff.
+pub async fn vendor_user_session_auth(
+    db: &Database,
+    req: &HttpRequest
+) -> Result<(VendorUser, Session), AppError> {
     let cookie = req.cookie("vendor").ok_or(AppError::Auth)?;
-    let user_id = cookie.value();
-    let object_id = ObjectId::parse_str(user_id)
-        .map_err(|_| AppError::InternalError)?;
-    let vendor = Vendor::find_by_id(&db, object_id).await?;
+    let mut session = Session::find_by_token_kinds(
+        db,
+        &[SessionKind::Vendor, SessionKind::Staff],
+        cookie.value()
+    ).await?;
+
+    let staff = match session.kind {
+        SessionKind::Staff => {
+            let staff = match Staff::find_by_id(db, session.account).await {
+                Ok(s) => s,
+                Err(AppError::NotFound(_)) => return Err(AppError::Auth),
+                Err(e) => return Err(e)
+            };
+            if staff.pass_hash.is_none() {
+                return Err(AppError::forbidden("Staff password not set"));
+            }
+            Some(staff)
+        },
+        _ => None
+    };
+
+    let vendor_id = staff.as_ref().map(|s| s.vendor).unwrap_or(session.account);
+    let vendor = match Vendor::find_by_id(db, vendor_id).await {
+        Ok(v) => v,
+        Err(AppError::NotFound(_)) => return Err(AppError::Auth),
+        Err(e) => return Err(e)
+    };
     if vendor.pass_hash.is_none() {
         return Err(AppError::forbidden("Vendor password not set"));
     }
-    Ok(vendor)
+    vendor.ensure_active()?;
+    session.touch(db, client_ip(req)).await?;
+    Ok((VendorUser {vendor, staff}, session))
+}
+
+pub async fn admin_auth(
+    db: &Database,
+    req: &HttpRequest
+) -> Result<Admin, AppError> {
+    let cookie = req.cookie("admin").ok_or(AppError::Auth)?;
+    let mut session = Session::find_by_token(db, SessionKind::Admin, cookie.value()).await?;
+    let admin = match Admin::find_by_id(db, session.account).await {
+        Ok(a) => a,
+        Err(AppError::NotFound(_)) => return Err(AppError::Auth),
+        Err(e) => return Err(e)
+    };
+    session.touch(db, client_ip(req)).await?;
+    Ok(admin)
+}
+
+pub async fn customer_auth(
+    db: &Database,
+    req: &HttpRequest
+) -> Result<Customer, AppError> {
+    let cookie = req.cookie("customer").ok_or(AppError::Auth)?;
+    let mut session = Session::find_by_token(db, SessionKind::Customer, cookie.value()).await?;
+    let customer = match Customer::find_by_id(db, session.account).await {
+        Ok(c) => c,
+        Err(AppError::NotFound(_)) => return Err(AppError::Auth),
+        Err(e) => return Err(e)
+    };
+    session.touch(db, client_ip(req)).await?;
+    Ok(customer)
+}
+
+pub fn operator_auth(req: &HttpRequest) -> Result<(), AppError> {
+    let expected = std::env::var("OPERATOR_TOKEN").unwrap_or_default();
+    let provided = bearer_token(req).ok_or(AppError::Auth)?;
+
+    //Compare digests so the comparison time doesn't depend on the secret
+    if expected.is_empty() || hash_token(provided) != hash_token(&expected) {
+        return Err(AppError::Auth);
+    }
+    Ok(())
+}
+
+pub fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controllers::vendor::common::create_vendor;
+
+    fn create_user(role: Option<Role>) -> VendorUser {
+        let vendor = create_vendor(true);
+        let staff = role.map(|r| {
+            Staff::new(vendor._id, String::from("jane@shop.com"), String::from("Jane"), r).unwrap()
+        });
+        VendorUser {vendor, staff}
+    }
+
+    //VendorUser::require
+    #[test]
+    fn owner_has_every_role() {
+        let user = create_user(None);
+        assert!(user.require(Role::Owner).is_ok());
+        assert!(user.require(Role::Inventory).is_ok());
+    }
+
+    #[test]
+    fn inventory_cannot_act_as_manager() {
+        let user = create_user(Some(Role::Inventory));
+        assert!(matches!(user.require(Role::Manager), Err(AppError::Forbidden(_))));
+        assert!(user.require(Role::Inventory).is_ok());
+    }
+
+    //VendorUser::role
+    #[test]
+    fn staff_role_is_used() {
+        let user = create_user(Some(Role::Manager));
+        assert_eq!(user.role(), Role::Manager);
+    }
 }
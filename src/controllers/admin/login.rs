@@ -0,0 +1,53 @@
+use actix_web::{HttpResponse, HttpRequest, post, web};
+use serde::Deserialize;
+use mongodb::Database;
+use crate::{
+    controllers::vendor::common,
+    models::{
+        admin::Admin,
+        session::{Session, SessionKind},
+        throttle
+    },
+    app_error::AppError,
+    helpers::{auth_cookie, client_ip, user_agent}
+};
+
+#[derive(Deserialize)]
+struct Body {
+    email: String,
+    password: String
+}
+
+#[post("/admin/login")]
+pub async fn route(
+    db: web::Data<Database>,
+    body: web::Json<Body>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    //Throttle
+    let guard = throttle::guard(&db, "admin_login", Some(&body.email), client_ip(&req)).await?;
+
+    //Logic
+    let admin = guard.settle(authenticate(&db, &body).await).await?;
+
+    let (_, token) = Session::create(
+        &db,
+        SessionKind::Admin,
+        admin._id,
+        user_agent(&req),
+        client_ip(&req)
+    ).await?;
+
+    //Respond
+    Ok(HttpResponse::Ok().cookie(auth_cookie("admin", token)).json(admin.response()))
+}
+
+async fn authenticate(db: &Database, body: &Body) -> Result<Admin, AppError> {
+    let admin = Admin::find_by_email(db, body.email.trim()).await?;
+    common::compare_password(&body.password, &admin.pass_hash)?;
+
+    if let Some(new_hash) = common::rehash_if_needed(&body.password, &admin.pass_hash)? {
+        admin.update_pass_hash(db, new_hash).await?;
+    }
+    Ok(admin)
+}
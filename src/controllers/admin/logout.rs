@@ -0,0 +1,22 @@
+use actix_web::{HttpResponse, HttpRequest, web, post};
+use mongodb::{Database, bson::doc};
+use crate::{
+    app_error::AppError,
+    models::session::Session,
+    helpers::removal_cookie
+};
+
+#[post("/admin/logout")]
+pub async fn route(
+    db: web::Data<Database>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    if let Some(cookie) = req.cookie("admin") {
+        Session::delete_by_token(&db, cookie.value()).await?;
+    }
+
+    let mut response = HttpResponse::Ok().json(doc!{"success": true});
+    response.add_removal_cookie(&removal_cookie("admin"))
+        .map_err(|_| AppError::InternalError)?;
+    Ok(response)
+}
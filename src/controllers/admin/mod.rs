@@ -0,0 +1,6 @@
+pub mod login;
+pub mod logout;
+pub mod me;
+
+pub mod vendors;
+pub mod products;
@@ -0,0 +1,39 @@
+use actix_web::{HttpResponse, HttpRequest, web, put};
+use mongodb::{Database, bson::{doc, oid::ObjectId}};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    app_error::AppError,
+    auth::admin_auth,
+    models::{product::Product, audit::{self, Actor, ActorKind, AuditEntry}},
+    helpers::client_ip
+};
+
+#[derive(Deserialize)]
+struct Body {
+    active: bool
+}
+
+#[put("/admin/products/{product_id}/active")]
+pub async fn route(
+    db: web::Data<Database>,
+    path: web::Path<String>,
+    body: web::Json<Body>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    let admin = admin_auth(&db, &req).await?;
+    let product_id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| AppError::invalid_input("Invalid product ID"))?;
+
+    let product = Product::set_active(&db, product_id, body.active).await?;
+    AuditEntry::record(
+        &db,
+        Actor::new(ActorKind::Admin, admin._id),
+        Some(product.vendor),
+        "product.set_active",
+        Some(product_id),
+        client_ip(&req),
+        audit::diff(&doc!{"active": product.active}, &doc!{"active": body.active})
+    ).await;
+    Ok(HttpResponse::Ok().json(json!({"id": product_id.to_string(), "active": body.active})))
+}
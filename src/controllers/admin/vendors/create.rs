@@ -0,0 +1,21 @@
+use actix_web::{HttpResponse, HttpRequest, web, post};
+use mongodb::Database;
+use crate::{
+    app_error::AppError,
+    auth::admin_auth,
+    models::{vendor::Vendor, audit::{Actor, ActorKind}},
+    dto::vendor::NewVendor,
+    helpers::client_ip
+};
+
+#[post("/admin/vendors")]
+pub async fn route(
+    db: web::Data<Database>,
+    body: web::Json<NewVendor>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    let admin = admin_auth(&db, &req).await?;
+
+    let vendor = Vendor::onboard(&db, &body, Actor::new(ActorKind::Admin, admin._id), client_ip(&req)).await?;
+    Ok(HttpResponse::Ok().json(vendor.admin_response()))
+}
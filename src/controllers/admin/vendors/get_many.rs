@@ -0,0 +1,127 @@
+use actix_web::{HttpResponse, HttpRequest, web, get};
+use mongodb::{Database, bson::{Bson, Document, doc}};
+use serde::Deserialize;
+use crate::{
+    app_error::AppError,
+    auth::admin_auth,
+    models::vendor::{Vendor, ResponseAdminVendor},
+    helpers::results_per_page
+};
+
+#[derive(Deserialize)]
+struct Parameters {
+    page: Option<u64>,
+    results: Option<u64>,
+    active: Option<bool>,
+    stripe: Option<bool>,
+    search: Option<String>
+}
+
+#[get("/admin/vendors")]
+pub async fn route(
+    db: web::Data<Database>,
+    query: web::Query<Parameters>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    admin_auth(&db, &req).await?;
+
+    let results_range: (u64, u64) = (10, 100);
+    let vendors: Vec<ResponseAdminVendor> = Vendor::search(
+        &db,
+        create_filter(&query),
+        query.page.unwrap_or(0),
+        results_per_page(results_range.0, results_range.1, query.results.unwrap_or(50))
+    )
+        .await?
+        .into_iter()
+        .map(Vendor::admin_response)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(vendors))
+}
+
+fn create_filter(query: &Parameters) -> Document {
+    let mut filter = Document::new();
+
+    if let Some(active) = query.active {
+        filter.insert("active", active);
+    }
+
+    if let Some(stripe) = query.stripe {
+        match stripe {
+            true => filter.insert("stripe.activated", true),
+            false => filter.insert("stripe.activated", doc!{"$ne": true})
+        };
+    }
+
+    if let Some(search) = query.search.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        let pattern = doc!{"$regex": escape_regex(search), "$options": "i"};
+        filter.insert("$or", vec![
+            Bson::Document(doc!{"store": pattern.clone()}),
+            Bson::Document(doc!{"owner": pattern.clone()}),
+            Bson::Document(doc!{"email": pattern.clone()}),
+            Bson::Document(doc!{"url": pattern})
+        ]);
+    }
+
+    filter
+}
+
+fn escape_regex(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_query() -> Parameters {
+        Parameters {
+            page: None,
+            results: None,
+            active: None,
+            stripe: None,
+            search: None
+        }
+    }
+
+    //create_filter
+    #[test]
+    fn empty_query_matches_all() {
+        let result = create_filter(&create_query());
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn filters_by_status() {
+        let mut query = create_query();
+        query.active = Some(false);
+        query.stripe = Some(true);
+
+        let result = create_filter(&query);
+        assert!(!result.get_bool("active").unwrap());
+        assert!(result.get_bool("stripe.activated").unwrap());
+    }
+
+    #[test]
+    fn searches_all_text_fields() {
+        let mut query = create_query();
+        query.search = Some(String::from("inlet"));
+
+        let result = create_filter(&query);
+        assert_eq!(result.get_array("$or").unwrap().len(), 4);
+    }
+
+    //escape_regex
+    #[test]
+    fn escapes_special_characters() {
+        assert_eq!(escape_regex("a.b*"), "a\\.b\\*");
+    }
+}
@@ -0,0 +1,23 @@
+use actix_web::{HttpResponse, HttpRequest, web, post};
+use mongodb::{Database, bson::oid::ObjectId};
+use serde_json::json;
+use crate::{
+    app_error::AppError,
+    auth::admin_auth,
+    models::{vendor::Vendor, audit::{Actor, ActorKind}},
+    helpers::client_ip
+};
+
+#[post("/admin/vendors/{vendor_id}/invite")]
+pub async fn route(
+    db: web::Data<Database>,
+    path: web::Path<String>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    let admin = admin_auth(&db, &req).await?;
+    let vendor_id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| AppError::invalid_input("Invalid vendor ID"))?;
+
+    Vendor::resend_invite(&db, vendor_id, Actor::new(ActorKind::Admin, admin._id), client_ip(&req)).await?;
+    Ok(HttpResponse::Ok().json(json!({"success": true})))
+}
@@ -0,0 +1,4 @@
+pub mod create;
+pub mod get_many;
+pub mod invite;
+pub mod set_active;
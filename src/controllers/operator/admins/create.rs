@@ -0,0 +1,44 @@
+use actix_web::{HttpResponse, HttpRequest, web, post};
+use mongodb::{Database, bson::{DateTime, oid::ObjectId}};
+use serde::Deserialize;
+use crate::{
+    app_error::AppError,
+    auth::operator_auth,
+    controllers::vendor::common,
+    models::admin::Admin,
+    helpers::normalize_email
+};
+
+#[derive(Deserialize)]
+struct Body {
+    email: String,
+    name: String,
+    password: String,
+    confirm_password: String
+}
+
+#[post("/operator/admins")]
+pub async fn route(
+    db: web::Data<Database>,
+    body: web::Json<Body>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    operator_auth(&req)?;
+    common::valid_password(&body.password, &body.confirm_password)?;
+
+    let email = normalize_email(&body.email)?;
+    if Admin::find_by_email(&db, &email).await.is_ok() {
+        return Err(AppError::conflict("An admin with this email already exists"));
+    }
+
+    let admin = Admin {
+        _id: ObjectId::new(),
+        email,
+        name: body.name.trim().to_string(),
+        pass_hash: common::hash_password(&body.password)?,
+        created_at: DateTime::now()
+    };
+    admin.insert(&db).await?;
+
+    Ok(HttpResponse::Ok().json(admin.response()))
+}
@@ -0,0 +1,89 @@
+use actix_web::{HttpResponse, HttpRequest, web, get};
+use mongodb::{Database, bson::{Document, oid::ObjectId}};
+use serde::Deserialize;
+use crate::{
+    app_error::AppError,
+    auth::operator_auth,
+    models::audit::{AuditEntry, ResponseAuditEntry},
+    helpers::results_per_page
+};
+
+#[derive(Deserialize)]
+struct Parameters {
+    page: Option<u64>,
+    results: Option<u64>,
+    vendor: Option<String>,
+    action: Option<String>
+}
+
+#[get("/operator/audit")]
+pub async fn route(
+    db: web::Data<Database>,
+    query: web::Query<Parameters>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    operator_auth(&req)?;
+
+    let results_range: (u64, u64) = (10, 100);
+    let entries: Vec<ResponseAuditEntry> = AuditEntry::find(
+        &db,
+        create_filter(&query)?,
+        query.page.unwrap_or(0),
+        results_per_page(results_range.0, results_range.1, query.results.unwrap_or(50))
+    )
+        .await?
+        .into_iter()
+        .map(AuditEntry::response)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(entries))
+}
+
+fn create_filter(query: &Parameters) -> Result<Document, AppError> {
+    let mut filter = Document::new();
+
+    if let Some(v) = &query.vendor {
+        let vendor = ObjectId::parse_str(v)
+            .map_err(|_| AppError::invalid_input("Invalid vendor ID"))?;
+        filter.insert("vendor", vendor);
+    }
+
+    if let Some(a) = &query.action {
+        filter.insert("action", a);
+    }
+
+    Ok(filter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(vendor: Option<&str>, action: Option<&str>) -> Parameters {
+        Parameters {
+            page: None,
+            results: None,
+            vendor: vendor.map(String::from),
+            action: action.map(String::from)
+        }
+    }
+
+    //create_filter
+    #[test]
+    fn empty_filter_without_params() {
+        assert!(create_filter(&params(None, None)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn filters_by_vendor_and_action() {
+        let filter = create_filter(&params(Some("6735f92ee4a3c2b14bd9f8a1"), Some("product.update"))).unwrap();
+        assert!(filter.get_object_id("vendor").is_ok());
+        assert_eq!(filter.get_str("action").unwrap(), "product.update");
+    }
+
+    #[test]
+    fn rejects_invalid_vendor() {
+        let result = create_filter(&params(Some("nope"), None));
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod admins;
+pub mod audit;
+pub mod vendors;
@@ -0,0 +1,21 @@
+use actix_web::{HttpResponse, HttpRequest, web, post};
+use mongodb::Database;
+use crate::{
+    app_error::AppError,
+    auth::operator_auth,
+    models::{vendor::Vendor, audit::Actor},
+    dto::vendor::NewVendor,
+    helpers::client_ip
+};
+
+#[post("/operator/vendors")]
+pub async fn route(
+    db: web::Data<Database>,
+    body: web::Json<NewVendor>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    operator_auth(&req)?;
+
+    let vendor = Vendor::onboard(&db, &body, Actor::operator(), client_ip(&req)).await?;
+    Ok(HttpResponse::Ok().json(vendor.admin_response()))
+}
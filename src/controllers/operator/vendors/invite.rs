@@ -0,0 +1,23 @@
+use actix_web::{HttpResponse, HttpRequest, web, post};
+use mongodb::{Database, bson::oid::ObjectId};
+use serde_json::json;
+use crate::{
+    app_error::AppError,
+    auth::operator_auth,
+    models::{vendor::Vendor, audit::Actor},
+    helpers::client_ip
+};
+
+#[post("/operator/vendors/{vendor_id}/invite")]
+pub async fn route(
+    db: web::Data<Database>,
+    path: web::Path<String>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    operator_auth(&req)?;
+    let vendor_id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| AppError::invalid_input("Invalid vendor ID"))?;
+
+    Vendor::resend_invite(&db, vendor_id, Actor::operator(), client_ip(&req)).await?;
+    Ok(HttpResponse::Ok().json(json!({"success": true})))
+}
@@ -0,0 +1,3 @@
+pub mod create;
+pub mod invite;
+pub mod set_active;
@@ -0,0 +1,37 @@
+use actix_web::{HttpResponse, HttpRequest, web, put};
+use mongodb::{Database, bson::oid::ObjectId};
+use serde::Deserialize;
+use crate::{
+    app_error::AppError,
+    auth::operator_auth,
+    models::{vendor::Vendor, audit::Actor},
+    helpers::client_ip
+};
+
+#[derive(Deserialize)]
+struct Body {
+    active: bool,
+    reason: Option<String>
+}
+
+#[put("/operator/vendors/{vendor_id}/active")]
+pub async fn route(
+    db: web::Data<Database>,
+    path: web::Path<String>,
+    body: web::Json<Body>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    operator_auth(&req)?;
+    let vendor_id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| AppError::invalid_input("Invalid vendor ID"))?;
+
+    let vendor = Vendor::change_active(
+        &db,
+        vendor_id,
+        body.active,
+        body.reason.as_deref(),
+        Actor::operator(),
+        client_ip(&req)
+    ).await?;
+    Ok(HttpResponse::Ok().json(vendor.admin_response()))
+}
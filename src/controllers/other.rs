@@ -2,7 +2,11 @@ use actix_web::{HttpResponse, web, get};
 use tokio::fs;
 use std::path::Path;
 
-use crate::app_error::AppError;
+use crate::{
+    app_error::AppError,
+    csrf::CSRF_COOKIE,
+    helpers::{auth_cookie, generate_token}
+};
 
 #[get("/documentation")]
 pub async fn documentation_route() -> Result<HttpResponse, AppError> {
@@ -38,6 +42,16 @@ pub async fn documents_route(path: web::Path<String>) -> Result<HttpResponse, Ap
     }
 }
 
+/// Issues a double submit token for clients that can't send an Origin header.
+/// The token goes in the X-CSRF-Token header of state changing requests.
+#[get("/csrf")]
+pub async fn csrf_route() -> Result<HttpResponse, AppError> {
+    let token = generate_token();
+    Ok(HttpResponse::Ok()
+        .cookie(auth_cookie(CSRF_COOKIE, token.clone()))
+        .json(serde_json::json!({"token": token})))
+}
+
 fn get_mime(path: &Path) -> String {
     match path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase().as_str() {
         "avif" => String::from("image/avif"),
@@ -0,0 +1,307 @@
+use actix_web::{HttpResponse, HttpRequest, web, post};
+use mongodb::{Database, bson::{Document, doc}};
+use serde_json::{Value, json};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::{
+    app_error::AppError,
+    models::{
+        vendor::Vendor,
+        product::Product,
+        reservation::Reservation,
+        checkout::Checkout,
+        order::{Order, Contact, Dispute},
+        customer::ShippingAddress,
+        stripe_event::StripeEvent,
+        audit::{self, Actor, AuditEntry}
+    },
+    helpers::{client_ip, stripe::verify_signature}
+};
+
+#[post("/stripe/webhook")]
+pub async fn route(
+    db: web::Data<Database>,
+    body: web::Bytes,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    //Verify
+    let header = req.headers()
+        .get("Stripe-Signature")
+        .and_then(|h| h.to_str().ok())
+        .ok_or(AppError::invalid_input("Missing Stripe signature"))?;
+    let secrets = std::env::var("STRIPE_WEBHOOK_SECRET")
+        .map_err(|_| AppError::InternalError)?;
+    verify_signature(&body, header, &secrets, now())?;
+
+    let event: Value = serde_json::from_slice(&body)
+        .map_err(|_| AppError::invalid_input("Invalid Stripe event"))?;
+    let id = str_field(&event, "id")?;
+    let kind = str_field(&event, "type")?;
+
+    //Handle each event once
+    if !StripeEvent::claim(&db, id, kind).await? {
+        return Ok(HttpResponse::Ok().json(json!({"received": true})));
+    }
+    if let Err(e) = handle_event(&db, kind, &event["data"]["object"], client_ip(&req)).await {
+        StripeEvent::release(&db, id).await?;
+        return Err(e);
+    }
+
+    Ok(HttpResponse::Ok().json(json!({"received": true})))
+}
+
+async fn handle_event(
+    db: &Database,
+    kind: &str,
+    object: &Value,
+    ip: Option<String>
+) -> Result<(), AppError> {
+    match kind {
+        "account.updated" => account_updated(db, object, ip).await,
+        "checkout.session.completed" | "checkout.session.async_payment_succeeded" => {
+            checkout_completed(db, object, ip).await
+        },
+        "checkout.session.expired" | "checkout.session.async_payment_failed" => {
+            checkout_abandoned(db, object).await
+        },
+        "charge.refunded" => charge_refunded(db, object, ip).await,
+        k if k.starts_with("charge.dispute.") => dispute_changed(db, object, ip).await,
+        _ => Ok(())
+    }
+}
+
+async fn account_updated(db: &Database, account: &Value, ip: Option<String>) -> Result<(), AppError> {
+    let activated = account_activated(account);
+    let before = match Vendor::set_stripe_activated(db, str_field(account, "id")?, activated).await? {
+        Some(v) => v,
+        None => return Ok(())
+    };
+
+    let changes = audit::set_diff(&audit::to_document(&before), &doc!{"stripe.activated": activated});
+    if !changes.is_empty() {
+        AuditEntry::record(
+            db,
+            Actor::stripe(),
+            Some(before._id),
+            "vendor.stripe_activated",
+            Some(before._id),
+            ip,
+            changes
+        ).await;
+    }
+    Ok(())
+}
+
+async fn checkout_completed(db: &Database, session: &Value, ip: Option<String>) -> Result<(), AppError> {
+    //Delayed payment methods complete later with async_payment_succeeded
+    if session["payment_status"].as_str() != Some("paid") {
+        return Ok(());
+    }
+
+    let checkout = match Checkout::find_by_session(db, str_field(session, "id")?).await {
+        Ok(c) => c,
+        Err(AppError::NotFound(_)) => return Ok(()),
+        Err(e) => return Err(e)
+    };
+    let reservations = checkout.reservations.clone();
+    let order = Order::from_checkout(
+        checkout,
+        session_contact(session),
+        session_shipping_address(session),
+        session["payment_intent"].as_str().map(String::from)
+    );
+
+    if order.insert(db).await? {
+        AuditEntry::record(
+            db,
+            Actor::stripe(),
+            Some(order.vendor),
+            "order.create",
+            Some(order._id),
+            ip,
+            doc!{"total": {"before": null, "after": order.total}}
+        ).await;
+
+        //Payment has been taken, so a shortfall is left for the vendor to resolve
+        for item in &order.items {
+            if let Err(e) = Product::decrement_stock(db, item.product, item.price, item.quantity).await {
+                eprintln!("Failed to take stock for order {}: {:?}", order._id, e);
+            }
+        }
+
+        //Retries can take a while, so don't hold up Stripe
+        let db = db.clone();
+        tokio::spawn(async move {
+            if let Err(e) = order.send_emails(&db).await {
+                eprintln!("Failed to send emails for order {}: {:?}", order._id, e);
+            }
+        });
+    }
+    Reservation::release(db, &reservations).await
+}
+
+async fn checkout_abandoned(db: &Database, session: &Value) -> Result<(), AppError> {
+    match Checkout::find_by_session(db, str_field(session, "id")?).await {
+        Ok(c) => Reservation::release(db, &c.reservations).await,
+        Err(AppError::NotFound(_)) => Ok(()),
+        Err(e) => Err(e)
+    }
+}
+
+async fn charge_refunded(db: &Database, charge: &Value, ip: Option<String>) -> Result<(), AppError> {
+    let payment_intent = match charge["payment_intent"].as_str() {
+        Some(p) => p,
+        None => return Ok(())
+    };
+    let refunded = charge["amount_refunded"].as_i64().unwrap_or(0);
+
+    match Order::set_refunded(db, payment_intent, refunded).await? {
+        Some(before) if before.refunded < refunded => {
+            record_order_change(db, &before, "order.refund", doc!{"refunded": refunded}, ip).await;
+        },
+        _ => ()
+    }
+    Ok(())
+}
+
+async fn dispute_changed(db: &Database, dispute: &Value, ip: Option<String>) -> Result<(), AppError> {
+    let payment_intent = match dispute["payment_intent"].as_str() {
+        Some(p) => p,
+        None => return Ok(())
+    };
+    let dispute = Dispute {
+        id: str_field(dispute, "id")?.to_string(),
+        status: dispute["status"].as_str().unwrap_or_default().to_string(),
+        reason: dispute["reason"].as_str().unwrap_or_default().to_string(),
+        amount: dispute["amount"].as_i64().unwrap_or(0)
+    };
+
+    if let Some(before) = Order::set_dispute(db, payment_intent, &dispute).await? {
+        let after = doc!{"dispute": audit::to_document(&dispute)};
+        record_order_change(db, &before, "order.dispute", after, ip).await;
+    }
+    Ok(())
+}
+
+async fn record_order_change(
+    db: &Database,
+    before: &Order,
+    action: &str,
+    set: Document,
+    ip: Option<String>
+) {
+    AuditEntry::record(
+        db,
+        Actor::stripe(),
+        Some(before.vendor),
+        action,
+        Some(before._id),
+        ip,
+        audit::set_diff(&audit::to_document(before), &set)
+    ).await;
+}
+
+fn account_activated(account: &Value) -> bool {
+    account["charges_enabled"].as_bool().unwrap_or(false)
+        && account["payouts_enabled"].as_bool().unwrap_or(false)
+}
+
+fn session_contact(session: &Value) -> Contact {
+    let details = &session["customer_details"];
+    let text = |v: &Value| v.as_str().map(String::from);
+
+    Contact {
+        name: text(&details["name"]).unwrap_or_default(),
+        email: text(&details["email"]).unwrap_or_default(),
+        phone: text(&details["phone"])
+    }
+}
+
+/// Newer API versions nest shipping under `collected_information`.
+fn session_shipping_address(session: &Value) -> Option<ShippingAddress> {
+    let details = match &session["collected_information"]["shipping_details"] {
+        Value::Null => &session["shipping_details"],
+        d => d
+    };
+    let address = &details["address"];
+    let text = |v: &Value| v.as_str().map(String::from);
+
+    Some(ShippingAddress {
+        name: text(&details["name"])?,
+        line1: text(&address["line1"])?,
+        line2: text(&address["line2"]),
+        city: text(&address["city"])?,
+        state: text(&address["state"]).unwrap_or_default(),
+        postal_code: text(&address["postal_code"])?,
+        country: text(&address["country"])?
+    })
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> Result<&'a str, AppError> {
+    value[key].as_str().ok_or(AppError::invalid_input("Invalid Stripe event"))
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //account_activated
+    #[test]
+    fn activated_needs_charges_and_payouts() {
+        assert!(account_activated(&json!({"charges_enabled": true, "payouts_enabled": true})));
+        assert!(!account_activated(&json!({"charges_enabled": true, "payouts_enabled": false})));
+        assert!(!account_activated(&json!({"id": "acct_123"})));
+    }
+
+    //session_contact
+    #[test]
+    fn reads_customer_details() {
+        let session = json!({"customer_details": {"name": "Jane Doe", "email": "jane@example.com", "phone": null}});
+        let contact = session_contact(&session);
+
+        assert_eq!(contact.name, "Jane Doe");
+        assert_eq!(contact.email, "jane@example.com");
+        assert!(contact.phone.is_none());
+    }
+
+    //session_shipping_address
+    #[test]
+    fn reads_collected_shipping_details() {
+        let session = json!({"collected_information": {"shipping_details": {
+            "name": "Jane Doe",
+            "address": {
+                "line1": "123 Main St",
+                "line2": null,
+                "city": "Wilmington",
+                "state": "NC",
+                "postal_code": "28401",
+                "country": "US"
+            }
+        }}});
+
+        let address = session_shipping_address(&session).unwrap();
+        assert_eq!(address.city, "Wilmington");
+        assert!(address.line2.is_none());
+    }
+
+    #[test]
+    fn reads_legacy_shipping_details() {
+        let session = json!({"shipping_details": {
+            "name": "Jane Doe",
+            "address": {"line1": "1 Dock Rd", "city": "Wilmington", "state": "NC", "postal_code": "28401", "country": "US"}
+        }});
+
+        assert!(session_shipping_address(&session).is_some());
+    }
+
+    #[test]
+    fn no_address_without_shipping() {
+        assert!(session_shipping_address(&json!({"shipping_details": null})).is_none());
+    }
+}
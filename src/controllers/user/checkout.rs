@@ -0,0 +1,487 @@
+use actix_web::{HttpResponse, HttpRequest, web, post};
+use mongodb::{Database, bson::{DateTime, doc, oid::ObjectId}};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+use crate::{
+    app_error::AppError,
+    auth::customer_auth,
+    models::{
+        product::{Product, PurchaseOption},
+        vendor::Vendor,
+        customer::Customer,
+        checkout::{Checkout, LineItem},
+        reservation::Reservation
+    },
+    controllers::user::reservations::common::{claim_hold, validate_quantity},
+    helpers::stripe::{application_fee, application_fee_bps},
+    stripe::{StripeClient, response_str}
+};
+
+const MAX_ITEMS: usize = 20;
+const CURRENCY: &str = "usd";
+/// Stripe needs at least 30 minutes, the extra minute covers the time it
+/// takes to reach Stripe. Reservations outlast this so stock is still held
+/// until the session expires.
+const SESSION_DURATION: Duration = Duration::from_secs(31 * 60);
+
+#[derive(Deserialize)]
+struct Body {
+    items: Vec<BodyItem>
+}
+
+#[derive(Deserialize)]
+struct BodyItem {
+    product: ObjectId,
+    price: ObjectId,
+    quantity: i32,
+    reservation: Option<String>
+}
+
+#[post("/user/checkout")]
+pub async fn route(
+    db: web::Data<Database>,
+    stripe: web::Data<StripeClient>,
+    body: web::Json<Body>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    //Shoppers can check out as a guest
+    let customer = match req.cookie("customer") {
+        Some(_) => Some(customer_auth(&db, &req).await?),
+        None => None
+    };
+
+    //Gather data
+    validate_items(&body.items)?;
+    let replacing = find_replaced(&db, &body.items).await?;
+    let replaced: Vec<ObjectId> = replacing.iter().flatten().copied().collect();
+    let quantity = body.items.iter().map(|i| i.quantity).sum();
+    let holder = claim_hold(&db, &req, customer.as_ref().map(|c| c._id), quantity, &replaced).await?;
+    let mut products = Vec::new();
+    for item in &body.items {
+        products.push(Product::find_active_by_id::<Product>(&db, item.product, doc!{}).await?);
+    }
+    let (vendor_id, items) = build_line_items(&body.items, &products)?;
+    let vendor = Vendor::find_by_id(&db, vendor_id).await?;
+    vendor.ensure_active()?;
+    let account = match &vendor.stripe {
+        Some(s) if s.activated => s.account_id.clone(),
+        _ => return Err(AppError::invalid_input("This store is not accepting payments"))
+    };
+
+    //Hold stock while the shopper pays
+    let reservations = hold_items(&db, &body.items, &products, &replacing, &holder).await?;
+    let expires_at = (DateTime::now().timestamp_millis() / 1000) + SESSION_DURATION.as_secs() as i64;
+
+    //Create the Stripe session
+    let subtotal: i64 = items.iter().map(LineItem::subtotal).sum();
+    let shipping: i64 = items.iter().map(LineItem::shipping_total).sum();
+    let mut checkout = Checkout {
+        _id: ObjectId::new(),
+        session_id: String::new(),
+        vendor: vendor._id,
+        customer: customer.as_ref().map(|c| c._id),
+        items,
+        reservations,
+        subtotal,
+        shipping,
+        application_fee: application_fee(subtotal, application_fee_bps()),
+        created_at: DateTime::now()
+    };
+    let mut params = checkout_params(&checkout, &vendor, &account, customer.as_ref());
+    params.push((String::from("expires_at"), expires_at.to_string()));
+    let (session_id, url) = match create_checkout_session(&stripe, checkout._id, params).await {
+        Ok(s) => s,
+        Err(e) => {
+            Reservation::release(&db, &checkout.reservations).await?;
+            return Err(e);
+        }
+    };
+
+    //Without the checkout the payment can't become an order, so close the
+    //session and free the stock
+    checkout.session_id = session_id;
+    if let Err(e) = checkout.insert(&db).await {
+        expire_checkout_session(&stripe, &checkout.session_id).await;
+        Reservation::release(&db, &checkout.reservations).await?;
+        return Err(e);
+    }
+    Ok(HttpResponse::Ok().json(json!({"id": checkout.session_id, "url": url})))
+}
+
+fn validate_items(items: &[BodyItem]) -> Result<(), AppError> {
+    if items.is_empty() {
+        return Err(AppError::invalid_input("Cart is empty"));
+    }
+
+    if items.len() > MAX_ITEMS {
+        return Err(AppError::invalid_input(&format!("A cart may hold at most {} items", MAX_ITEMS)));
+    }
+
+    for (i, item) in items.iter().enumerate() {
+        validate_quantity(item.quantity)?;
+
+        if items[..i].iter().any(|other| other.price == item.price) {
+            return Err(AppError::invalid_input("Each price may only appear once in the cart"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks every item against its product and snapshots it. All items must
+/// come from one vendor since the payment goes to a single Stripe account.
+fn build_line_items(
+    items: &[BodyItem],
+    products: &[Product]
+) -> Result<(ObjectId, Vec<LineItem>), AppError> {
+    let vendor = products.first().map(|p| p.vendor).ok_or(AppError::invalid_input("Cart is empty"))?;
+    let mut line_items = Vec::new();
+
+    for (item, product) in items.iter().zip(products) {
+        if product.vendor != vendor {
+            return Err(AppError::invalid_input("All items must be from the same store"));
+        }
+
+        let price = product.prices.iter()
+            .find(|p| p._id == item.price && !p.archived)
+            .filter(|_| !product.archived)
+            .ok_or(AppError::not_found("Price does not exist"))?;
+
+        let shipping = match price.purchase_option {
+            PurchaseOption::Ship => price.shipping,
+            PurchaseOption::Buy => 0,
+            PurchaseOption::List => return Err(AppError::invalid_input(&format!("{} cannot be purchased online", product.name)))
+        };
+
+        if item.quantity > price.quantity {
+            return Err(AppError::OutOfStock(price.quantity.max(0)));
+        }
+
+        line_items.push(LineItem {
+            product: product._id,
+            price: price._id,
+            name: product.name.clone(),
+            descriptor: price.descriptor.clone(),
+            unit_price: price.price,
+            shipping,
+            quantity: item.quantity
+        });
+    }
+
+    Ok((vendor, line_items))
+}
+
+/// Looks up the reservations the shopper made beforehand, one entry per
+/// item.
+async fn find_replaced(db: &Database, items: &[BodyItem]) -> Result<Vec<Option<ObjectId>>, AppError> {
+    let mut replacing = Vec::new();
+
+    for item in items {
+        match &item.reservation {
+            Some(token) => {
+                let existing = Reservation::find_by_token(db, token).await?;
+                if existing.price != item.price {
+                    return Err(AppError::invalid_input("Reservation is for a different price"));
+                }
+                replacing.push(Some(existing._id));
+            },
+            None => replacing.push(None)
+        }
+    }
+
+    Ok(replacing)
+}
+
+/// Reserves every item for the life of the Checkout Session. Reservations
+/// the shopper made beforehand are swapped for the new ones.
+async fn hold_items(
+    db: &Database,
+    items: &[BodyItem],
+    products: &[Product],
+    replacing: &[Option<ObjectId>],
+    holder: &str
+) -> Result<Vec<ObjectId>, AppError> {
+    let mut held = Vec::new();
+
+    for ((item, product), replacing) in items.iter().zip(products).zip(replacing) {
+        match hold_item(db, item, product, *replacing, holder).await {
+            Ok(id) => held.push(id),
+            Err(e) => {
+                Reservation::release(db, &held).await?;
+                return Err(e);
+            }
+        }
+    }
+
+    let replaced: Vec<ObjectId> = replacing.iter().flatten().copied().collect();
+    Reservation::release(db, &replaced).await?;
+    Ok(held)
+}
+
+async fn hold_item(
+    db: &Database,
+    item: &BodyItem,
+    product: &Product,
+    replacing: Option<ObjectId>,
+    holder: &str
+) -> Result<ObjectId, AppError> {
+    let stock = product.prices.iter()
+        .find(|p| p._id == item.price)
+        .map(|p| p.quantity)
+        .unwrap_or(0);
+
+    let reservation = Reservation::new(product._id, item.price, item.quantity).held_by(holder.to_string());
+    reservation.hold(db, stock, replacing).await?;
+    Ok(reservation._id)
+}
+
+/// Form parameters for a Checkout Session that charges on the platform and
+/// transfers to the vendor's connected account minus the application fee.
+fn checkout_params(
+    checkout: &Checkout,
+    vendor: &Vendor,
+    account: &str,
+    customer: Option<&Customer>
+) -> Vec<(String, String)> {
+    let mut params = vec![
+        (String::from("mode"), String::from("payment")),
+        (String::from("success_url"), format!("https://inlet.shop/{}/checkout/success?session_id={{CHECKOUT_SESSION_ID}}", vendor.url)),
+        (String::from("cancel_url"), format!("https://inlet.shop/{}/cart", vendor.url)),
+        (String::from("payment_intent_data[application_fee_amount]"), checkout.application_fee.to_string()),
+        (String::from("payment_intent_data[transfer_data][destination]"), account.to_string()),
+        (String::from("payment_intent_data[metadata][checkout]"), checkout._id.to_hex()),
+        (String::from("metadata[checkout]"), checkout._id.to_hex()),
+        (String::from("metadata[vendor]"), vendor._id.to_hex())
+    ];
+
+    for (i, item) in checkout.items.iter().enumerate() {
+        let key = |k: &str| format!("line_items[{}]{}", i, k);
+        params.push((key("[quantity]"), item.quantity.to_string()));
+        params.push((key("[price_data][currency]"), CURRENCY.to_string()));
+        params.push((key("[price_data][unit_amount]"), item.unit_price.to_string()));
+        params.push((key("[price_data][product_data][name]"), format!("{} ({})", item.name, item.descriptor)));
+    }
+
+    if checkout.items.iter().any(|i| i.shipping > 0) {
+        params.push((String::from("shipping_address_collection[allowed_countries][0]"), String::from("US")));
+        params.push((String::from("shipping_options[0][shipping_rate_data][type]"), String::from("fixed_amount")));
+        params.push((String::from("shipping_options[0][shipping_rate_data][display_name]"), String::from("Shipping")));
+        params.push((String::from("shipping_options[0][shipping_rate_data][fixed_amount][amount]"), checkout.shipping.to_string()));
+        params.push((String::from("shipping_options[0][shipping_rate_data][fixed_amount][currency]"), CURRENCY.to_string()));
+    }
+
+    if let Some(c) = customer {
+        params.push((String::from("customer_email"), c.email.clone()));
+        params.push((String::from("client_reference_id"), c._id.to_hex()));
+    }
+
+    params
+}
+
+/// Every request holds its own stock and gets its own checkout, so the key
+/// only ever covers this one call.
+async fn create_checkout_session(
+    stripe: &StripeClient,
+    checkout: ObjectId,
+    params: Vec<(String, String)>
+) -> Result<(String, String), AppError> {
+    let data = stripe.create(
+        "/v1/checkout/sessions",
+        params,
+        &format!("checkout-{}", checkout),
+        None
+    ).await?;
+
+    let id = response_str(&data, "id")?.to_string();
+    let url = response_str(&data, "url")?.to_string();
+    Ok((id, url))
+}
+
+async fn expire_checkout_session(stripe: &StripeClient, session_id: &str) {
+    let result = stripe.create(
+        &format!("/v1/checkout/sessions/{}/expire", session_id),
+        Vec::new(),
+        &format!("expire-{}", session_id),
+        None
+    ).await;
+    if let Err(e) = result {
+        eprintln!("Failed to expire checkout session {}: {:?}", session_id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::product::Price;
+    use crate::controllers::vendor::common::create_vendor;
+    use crate::models::reservation::RESERVATION_DURATION;
+    use crate::stripe::fake::FakeTransport;
+
+    fn product(vendor: ObjectId, option: PurchaseOption) -> Product {
+        Product {
+            _id: ObjectId::new(),
+            vendor,
+            name: String::from("Live Bait"),
+            tags: Vec::new(),
+            images: Vec::new(),
+            thumbnail: None,
+            active: true,
+            archived: false,
+            created_at: DateTime::now(),
+            prices: vec![Price {
+                _id: ObjectId::new(),
+                descriptor: String::from("Dozen"),
+                price: 1200,
+                quantity: 5,
+                shipping: 300,
+                images: Vec::new(),
+                purchase_option: option,
+                archived: false
+            }]
+        }
+    }
+
+    fn item(product: &Product, quantity: i32) -> BodyItem {
+        BodyItem {
+            product: product._id,
+            price: product.prices[0]._id,
+            quantity,
+            reservation: None
+        }
+    }
+
+    //validate_items
+    #[test]
+    fn rejects_empty_cart() {
+        let result = validate_items(&[]);
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn rejects_zero_quantity() {
+        let p = product(ObjectId::new(), PurchaseOption::Ship);
+        let result = validate_items(&[item(&p, 0)]);
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn rejects_duplicate_prices() {
+        let p = product(ObjectId::new(), PurchaseOption::Ship);
+        let result = validate_items(&[item(&p, 1), item(&p, 2)]);
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    //build_line_items
+    #[test]
+    fn snapshots_items() {
+        let p = product(ObjectId::new(), PurchaseOption::Ship);
+        let (vendor, items) = build_line_items(&[item(&p, 2)], &[p]).unwrap();
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].subtotal(), 2400);
+        assert_eq!(items[0].shipping_total(), 600);
+        assert_eq!(items[0].descriptor, "Dozen");
+        assert_ne!(vendor, items[0].product);
+    }
+
+    #[test]
+    fn buy_items_have_no_shipping() {
+        let p = product(ObjectId::new(), PurchaseOption::Buy);
+        let (_, items) = build_line_items(&[item(&p, 1)], &[p]).unwrap();
+        assert_eq!(items[0].shipping, 0);
+    }
+
+    #[test]
+    fn rejects_list_only_prices() {
+        let p = product(ObjectId::new(), PurchaseOption::List);
+        let result = build_line_items(&[item(&p, 1)], &[p]);
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn rejects_more_than_available() {
+        let p = product(ObjectId::new(), PurchaseOption::Ship);
+        let result = build_line_items(&[item(&p, 6)], &[p]);
+        assert!(matches!(result, Err(AppError::OutOfStock(5))));
+    }
+
+    #[test]
+    fn rejects_archived_price() {
+        let mut p = product(ObjectId::new(), PurchaseOption::Ship);
+        p.prices[0].archived = true;
+        let result = build_line_items(&[item(&p, 1)], &[p]);
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn rejects_multiple_vendors() {
+        let a = product(ObjectId::new(), PurchaseOption::Ship);
+        let b = product(ObjectId::new(), PurchaseOption::Ship);
+        let result = build_line_items(&[item(&a, 1), item(&b, 1)], &[a, b]);
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    //checkout_params
+    fn checkout(items: Vec<LineItem>) -> Checkout {
+        Checkout {
+            _id: ObjectId::new(),
+            session_id: String::new(),
+            vendor: ObjectId::new(),
+            customer: None,
+            reservations: Vec::new(),
+            subtotal: items.iter().map(LineItem::subtotal).sum(),
+            shipping: items.iter().map(LineItem::shipping_total).sum(),
+            application_fee: 120,
+            items,
+            created_at: DateTime::now()
+        }
+    }
+
+    fn param<'a>(params: &'a [(String, String)], key: &str) -> Option<&'a str> {
+        params.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    #[test]
+    fn sends_destination_charge() {
+        let p = product(ObjectId::new(), PurchaseOption::Ship);
+        let (_, items) = build_line_items(&[item(&p, 2)], &[p]).unwrap();
+        let params = checkout_params(&checkout(items), &create_vendor(true), "acct_123", None);
+
+        assert_eq!(param(&params, "payment_intent_data[transfer_data][destination]"), Some("acct_123"));
+        assert_eq!(param(&params, "payment_intent_data[application_fee_amount]"), Some("120"));
+        assert_eq!(param(&params, "line_items[0][quantity]"), Some("2"));
+        assert_eq!(param(&params, "line_items[0][price_data][unit_amount]"), Some("1200"));
+        assert_eq!(param(&params, "shipping_options[0][shipping_rate_data][fixed_amount][amount]"), Some("600"));
+    }
+
+    //SESSION_DURATION
+    #[test]
+    fn session_meets_stripe_minimum_and_ends_before_reservations() {
+        assert!(SESSION_DURATION > Duration::from_secs(30 * 60));
+        assert!(SESSION_DURATION < RESERVATION_DURATION);
+    }
+
+    //expire_checkout_session
+    #[tokio::test]
+    async fn expires_session_on_platform() {
+        let fake = FakeTransport::new();
+        fake.respond(200, json!({"id": "cs_123", "status": "expired"}));
+        let stripe = StripeClient::new(fake.clone());
+
+        expire_checkout_session(&stripe, "cs_123").await;
+
+        let request = &fake.requests()[0];
+        assert_eq!(request.path, "/v1/checkout/sessions/cs_123/expire");
+        assert!(request.account.is_none());
+    }
+
+    #[test]
+    fn skips_shipping_for_buy_items() {
+        let p = product(ObjectId::new(), PurchaseOption::Buy);
+        let (_, items) = build_line_items(&[item(&p, 1)], &[p]).unwrap();
+        let params = checkout_params(&checkout(items), &create_vendor(true), "acct_123", None);
+
+        assert!(param(&params, "shipping_address_collection[allowed_countries][0]").is_none());
+    }
+}
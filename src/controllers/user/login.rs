@@ -0,0 +1,81 @@
+use actix_web::{HttpResponse, HttpRequest, web, post, cookie::Cookie};
+use mongodb::{Database, bson::doc};
+use serde::Deserialize;
+use crate::{
+    app_error::AppError,
+    controllers::vendor::common,
+    models::{
+        customer::Customer,
+        session::{Session, SessionKind},
+        throttle
+    },
+    helpers::{auth_cookie, client_ip, user_agent}
+};
+
+#[derive(Deserialize)]
+struct Body {
+    email: String,
+    password: String
+}
+
+#[post("/user/login")]
+pub async fn route(
+    db: web::Data<Database>,
+    body: web::Json<Body>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    //Throttle
+    let guard = throttle::guard(&db, "customer_login", Some(&body.email), client_ip(&req)).await?;
+
+    //Logic
+    //Don't reveal whether the email has an account
+    let result = authenticate(&db, &body).await.map_err(|e| match e {
+        AppError::NotFound(_) => AppError::Auth,
+        e => e
+    });
+    let customer = guard.settle(result).await?;
+
+    //Respond
+    issue_session(&db, customer, &req).await
+}
+
+async fn authenticate(db: &Database, body: &Body) -> Result<Customer, AppError> {
+    let customer = Customer::find_by_email(db, &body.email).await?;
+    common::compare_password(&body.password, &customer.pass_hash)?;
+
+    if let Some(new_hash) = common::rehash_if_needed(&body.password, &customer.pass_hash)? {
+        return customer.update(db, doc!{"pass_hash": new_hash}).await;
+    }
+    Ok(customer)
+}
+
+pub async fn issue_session(
+    db: &Database,
+    customer: Customer,
+    req: &HttpRequest
+) -> Result<HttpResponse, AppError> {
+    let (_, token) = Session::create(
+        db,
+        SessionKind::Customer,
+        customer._id,
+        user_agent(req),
+        client_ip(req)
+    ).await?;
+
+    Ok(HttpResponse::Ok().cookie(set_auth_cookie(token)).json(customer.response()))
+}
+
+pub fn set_auth_cookie(token: String) -> Cookie<'static> {
+    auth_cookie("customer", token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cookie_correct_name() {
+        let result = set_auth_cookie(String::from("token"));
+        assert_eq!(result.name(), "customer");
+    }
+}
@@ -0,0 +1,15 @@
+use actix_web::{HttpResponse, HttpRequest, web, get};
+use mongodb::Database;
+use crate::{
+    app_error::AppError,
+    auth::customer_auth
+};
+
+#[get("/user/me")]
+pub async fn route(
+    db: web::Data<Database>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    let customer = customer_auth(&db, &req).await?;
+    Ok(HttpResponse::Ok().json(customer.response()))
+}
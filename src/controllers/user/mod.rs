@@ -1,2 +1,12 @@
+pub mod register;
+pub mod login;
+pub mod logout;
+pub mod me;
+pub mod update;
+pub mod password_email;
+pub mod reset_password;
+pub mod checkout;
+
 pub mod products;
+pub mod reservations;
 pub mod vendors;
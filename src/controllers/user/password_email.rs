@@ -0,0 +1,50 @@
+use actix_web::{HttpResponse, HttpRequest, web, post};
+use serde::Deserialize;
+use serde_json::json;
+use mongodb::Database;
+use crate::{
+    app_error::AppError,
+    models::{
+        customer::Customer,
+        token::{Token, TokenPurpose},
+        throttle
+    },
+    emails::{send_email, send_in_background, customer_reset_password},
+    helpers::client_ip
+};
+
+#[derive(Deserialize)]
+struct Body {
+    email: String
+}
+
+#[post("/user/password/reset")]
+pub async fn route(
+    db: web::Data<Database>,
+    body: web::Json<Body>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    let email = body.into_inner().email;
+    throttle::guard(&db, "customer_password_email", Some(&email), client_ip(&req)).await?.count().await?;
+
+    let db = db.get_ref().clone();
+    send_in_background("customer password reset", async move { send_reset(&db, &email).await });
+
+    Ok(HttpResponse::Ok().json(json!({"success": true})))
+}
+
+async fn send_reset(db: &Database, email: &str) -> Result<(), AppError> {
+    let customer = match Customer::find_by_email(db, email).await {
+        Ok(c) => c,
+        Err(AppError::NotFound(_)) => return Ok(()),
+        Err(e) => return Err(e)
+    };
+
+    let token = Token::issue(db, customer._id, TokenPurpose::CustomerReset).await?;
+    send_email(
+        &customer.email,
+        &customer.name,
+        "Reset Password for Inlet.Shop",
+        customer_reset_password(customer.name.clone(), customer._id.to_string(), token)
+    ).await
+}
@@ -2,7 +2,7 @@ use actix_web::{HttpResponse, web, get};
 use mongodb::{Database, bson::oid::ObjectId};
 use crate::{
     app_error::AppError,
-    models::product::Product,
+    models::{product::Product, vendor::Vendor, reservation::Reservation},
     dto::product::{ProductDb, ProductResponse}
 };
 
@@ -13,13 +13,14 @@ pub async fn route(
 ) -> Result<HttpResponse, AppError> {
     let id = ObjectId::parse_str(path.into_inner())
         .map_err(|_| AppError::invalid_input("Invalid product ID"))?;
-    let product: ProductResponse = Product::find_by_id::<ProductDb>(
+    let product = Product::find_active_by_id::<ProductDb>(
         &db,
         id,
-        None,
         ProductDb::projection(),
-    ).await?.into();
+    ).await?;
+    Vendor::verify_active(&db, product.vendor()).await?;
+    let reserved = Reservation::reserved(&db, &product.price_ids()).await?;
 
-    Ok(HttpResponse::Ok().json(product))
+    Ok(HttpResponse::Ok().json(ProductResponse::new(product, &reserved)))
 }
 
@@ -0,0 +1,49 @@
+use actix_web::{HttpResponse, HttpRequest, web, post};
+use mongodb::Database;
+use serde::Deserialize;
+use crate::{
+    app_error::AppError,
+    controllers::{vendor::common, user::login::issue_session},
+    models::{
+        customer::Customer,
+        throttle
+    },
+    helpers::{client_ip, normalize_email}
+};
+
+#[derive(Deserialize)]
+struct Body {
+    email: String,
+    name: String,
+    password: String,
+    confirm_password: String
+}
+
+#[post("/user/register")]
+pub async fn route(
+    db: web::Data<Database>,
+    body: web::Json<Body>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    //Limit sign ups per IP
+    throttle::guard(&db, "register", None, client_ip(&req)).await?.count().await?;
+
+    //Logic
+    let email = normalize_email(&body.email)?;
+    if body.name.trim().is_empty() {
+        return Err(AppError::invalid_input("Name is required"));
+    }
+    common::valid_password(&body.password, &body.confirm_password)?;
+
+    let customer = Customer::new(
+        email,
+        body.name.trim().to_string(),
+        common::hash_password(&body.password)?
+    );
+    //A taken email gets a 409 rather than a generic response. Registering
+    //logs straight in, so there's no way to answer both cases the same, and
+    //the per IP limit above slows anyone probing for accounts.
+    customer.insert(&db).await?;
+
+    issue_session(&db, customer, &req).await
+}
@@ -0,0 +1,73 @@
+use actix_web::HttpRequest;
+use mongodb::{Database, bson::oid::ObjectId};
+use crate::{
+    app_error::AppError,
+    models::{
+        reservation::{Reservation, MAX_QUANTITY, MAX_HELD},
+        throttle
+    },
+    helpers::client_ip
+};
+
+/// Holding stock is free, so every route that holds it goes through here to
+/// stop one client from holding everything. Logged in shoppers are limited
+/// by account, guests by address. Checks that `quantity` more units fit
+/// under the shopper's cap, leaving out the reservations being `replaced`,
+/// and returns the holder to save on the new reservations.
+pub async fn claim_hold(
+    db: &Database,
+    req: &HttpRequest,
+    customer: Option<ObjectId>,
+    quantity: i32,
+    replaced: &[ObjectId]
+) -> Result<String, AppError> {
+    let customer = customer.map(|c| c.to_hex());
+    let ip = client_ip(req);
+    let holder = holder(customer.as_deref(), ip.as_deref());
+    throttle::guard(db, "reservation", customer.as_deref(), ip).await?.count().await?;
+
+    let held = Reservation::held_by_holder(db, &holder, replaced).await?;
+    if held + quantity > MAX_HELD {
+        return Err(AppError::invalid_input(&format!("You can hold at most {} units at once", MAX_HELD)));
+    }
+    Ok(holder)
+}
+
+pub fn validate_quantity(quantity: i32) -> Result<(), AppError> {
+    if quantity < 1 {
+        return Err(AppError::invalid_input("Quantity must be at least 1"));
+    }
+
+    if quantity > MAX_QUANTITY {
+        return Err(AppError::invalid_input(&format!("A reservation may hold at most {} units", MAX_QUANTITY)));
+    }
+
+    Ok(())
+}
+
+fn holder(customer: Option<&str>, ip: Option<&str>) -> String {
+    match customer {
+        Some(c) => format!("customer:{}", c),
+        None => format!("ip:{}", ip.unwrap_or("unknown"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //holder
+    #[test]
+    fn prefers_customer_over_ip() {
+        assert_eq!(holder(Some("abc"), Some("1.2.3.4")), "customer:abc");
+        assert_eq!(holder(None, Some("1.2.3.4")), "ip:1.2.3.4");
+    }
+
+    //validate_quantity
+    #[test]
+    fn rejects_quantity_out_of_range() {
+        assert!(matches!(validate_quantity(0), Err(AppError::InvalidInput(_))));
+        assert!(matches!(validate_quantity(MAX_QUANTITY + 1), Err(AppError::InvalidInput(_))));
+        assert!(validate_quantity(MAX_QUANTITY).is_ok());
+    }
+}
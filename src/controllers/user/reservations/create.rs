@@ -0,0 +1,108 @@
+use actix_web::{HttpResponse, HttpRequest, web, post};
+use mongodb::{Database, bson::{doc, oid::ObjectId}};
+use serde::Deserialize;
+use crate::{
+    app_error::AppError,
+    auth::customer_auth,
+    models::{
+        product::{Product, PurchaseOption},
+        vendor::Vendor,
+        reservation::Reservation
+    },
+    controllers::user::reservations::common::{claim_hold, validate_quantity}
+};
+
+#[derive(Deserialize)]
+struct Body {
+    product: ObjectId,
+    price: ObjectId,
+    quantity: i32
+}
+
+#[post("/user/reservations")]
+pub async fn route(
+    db: web::Data<Database>,
+    body: web::Json<Body>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    let customer = match req.cookie("customer") {
+        Some(_) => Some(customer_auth(&db, &req).await?._id),
+        None => None
+    };
+    let holder = claim_hold(&db, &req, customer, body.quantity, &[]).await?;
+    validate_quantity(body.quantity)?;
+
+    let product = Product::find_active_by_id::<Product>(&db, body.product, doc!{}).await?;
+    let stock = purchasable_stock(&product, body.price)?;
+    Vendor::verify_active(&db, product.vendor).await?;
+
+    let (reservation, token) = Reservation::new(product._id, body.price, body.quantity)
+        .held_by(holder)
+        .with_token();
+    reservation.hold(&db, stock, None).await?;
+    Ok(HttpResponse::Ok().json(reservation.response(token)))
+}
+
+fn purchasable_stock(product: &Product, price: ObjectId) -> Result<i32, AppError> {
+    let price = product.prices.iter()
+        .find(|p| p._id == price && !p.archived)
+        .filter(|_| !product.archived)
+        .ok_or(AppError::not_found("Price does not exist"))?;
+
+    match price.purchase_option {
+        PurchaseOption::List => Err(AppError::invalid_input(&format!("{} cannot be purchased online", product.name))),
+        _ => Ok(price.quantity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::DateTime;
+    use crate::models::product::Price;
+
+    fn product(option: PurchaseOption) -> Product {
+        Product {
+            _id: ObjectId::new(),
+            vendor: ObjectId::new(),
+            name: String::from("Live Bait"),
+            tags: Vec::new(),
+            images: Vec::new(),
+            thumbnail: None,
+            active: true,
+            archived: false,
+            created_at: DateTime::now(),
+            prices: vec![Price {
+                _id: ObjectId::new(),
+                descriptor: String::from("Dozen"),
+                price: 1200,
+                quantity: 5,
+                shipping: 300,
+                images: Vec::new(),
+                purchase_option: option,
+                archived: false
+            }]
+        }
+    }
+
+    //purchasable_stock
+    #[test]
+    fn returns_price_quantity() {
+        let p = product(PurchaseOption::Buy);
+        assert_eq!(purchasable_stock(&p, p.prices[0]._id).unwrap(), 5);
+    }
+
+    #[test]
+    fn rejects_list_only_price() {
+        let p = product(PurchaseOption::List);
+        let result = purchasable_stock(&p, p.prices[0]._id);
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn rejects_unknown_price() {
+        let p = product(PurchaseOption::Ship);
+        let result = purchasable_stock(&p, ObjectId::new());
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+}
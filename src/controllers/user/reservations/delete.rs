@@ -0,0 +1,17 @@
+use actix_web::{HttpResponse, web, delete};
+use mongodb::Database;
+use serde_json::json;
+use crate::{
+    app_error::AppError,
+    models::reservation::Reservation
+};
+
+#[delete("/user/reservations/{token}")]
+pub async fn route(
+    db: web::Data<Database>,
+    path: web::Path<String>
+) -> Result<HttpResponse, AppError> {
+    let reservation = Reservation::find_by_token(&db, &path.into_inner()).await?;
+    Reservation::release(&db, &[reservation._id]).await?;
+    Ok(HttpResponse::Ok().json(json!({"success": true})))
+}
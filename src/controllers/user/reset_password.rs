@@ -0,0 +1,45 @@
+use actix_web::{HttpResponse, HttpRequest, web, put};
+use serde::Deserialize;
+use serde_json::json;
+use mongodb::{
+    Database,
+    bson::{doc, oid::ObjectId}
+};
+use crate::{
+    app_error::AppError,
+    controllers::vendor::common,
+    models::{
+        customer::Customer,
+        session::Session,
+        token::{Token, TokenPurpose},
+        throttle
+    },
+    helpers::client_ip
+};
+
+#[derive(Deserialize)]
+struct Body {
+    customer: ObjectId,
+    token: String,
+    password: String,
+    confirm_password: String
+}
+
+#[put("/user/password/reset")]
+pub async fn route(
+    db: web::Data<Database>,
+    body: web::Json<Body>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    let guard = throttle::guard(&db, "customer_reset_password", Some(&body.customer.to_hex()), client_ip(&req)).await?;
+
+    let customer = Customer::find_by_id(&db, body.customer).await?;
+    common::valid_password(&body.password, &body.confirm_password)?;
+    let pass_hash = common::hash_password(&body.password)?;
+
+    guard.settle(Token::consume(&db, customer._id, TokenPurpose::CustomerReset, &body.token).await).await?;
+
+    customer.update(&db, doc!{"pass_hash": pass_hash}).await?;
+    Session::delete_all(&db, customer._id, None).await?;
+    Ok(HttpResponse::Ok().json(json!({"success": true})))
+}
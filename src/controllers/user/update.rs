@@ -0,0 +1,92 @@
+use actix_web::{HttpResponse, HttpRequest, web, put};
+use mongodb::{Database, bson::{self, Bson, Document}};
+use serde::Deserialize;
+use crate::{
+    app_error::AppError,
+    auth::customer_auth,
+    models::customer::ShippingAddress
+};
+
+#[derive(Deserialize)]
+struct Body {
+    name: Option<String>,
+    phone: Option<String>,
+    shipping_address: Option<ShippingAddress>
+}
+
+#[put("/user/me")]
+pub async fn route(
+    db: web::Data<Database>,
+    body: web::Json<Body>,
+    req: HttpRequest
+) -> Result<HttpResponse, AppError> {
+    let customer = customer_auth(&db, &req).await?;
+    let update_doc = create_update_doc(body.into_inner())?;
+
+    let customer = customer.update(&db, update_doc).await?;
+    Ok(HttpResponse::Ok().json(customer.response()))
+}
+
+fn create_update_doc(body: Body) -> Result<Document, AppError> {
+    let mut doc = Document::new();
+
+    if let Some(name) = body.name {
+        if name.trim().is_empty() {
+            return Err(AppError::invalid_input("Name is required"));
+        }
+        doc.insert("name", name.trim());
+    }
+
+    if let Some(phone) = body.phone {
+        let phone = phone.trim();
+        doc.insert("phone", if phone.is_empty() { Bson::Null } else { Bson::String(phone.to_string()) });
+    }
+
+    if let Some(address) = body.shipping_address {
+        doc.insert("shipping_address", bson::to_bson(&address).map_err(|_| AppError::InternalError)?);
+    }
+
+    Ok(doc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_body() -> Body {
+        Body {
+            name: None,
+            phone: None,
+            shipping_address: None
+        }
+    }
+
+    //create_update_doc
+    #[test]
+    fn only_sets_given_fields() {
+        let mut body = create_body();
+        body.name = Some(String::from(" Jane Doe "));
+
+        let result = create_update_doc(body).unwrap();
+        assert_eq!(result.get_str("name").unwrap(), "Jane Doe");
+        assert!(!result.contains_key("phone"));
+    }
+
+    #[test]
+    fn rejects_empty_name() {
+        let mut body = create_body();
+        body.name = Some(String::from("  "));
+
+        let result = create_update_doc(body);
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn empty_phone_is_removed() {
+        let mut body = create_body();
+        body.phone = Some(String::new());
+
+        let result = create_update_doc(body).unwrap();
+        assert_eq!(result.get("phone"), Some(&Bson::Null));
+    }
+}
@@ -79,15 +79,10 @@ fn create_response(vendors: Vec<Document>) -> Vec<ResponseVendor> {
             id: v.get_object_id("_id").unwrap().to_hex(),
             store: v.get_str("store").unwrap_or("").to_string(),
             url: v.get_str("url").ok().unwrap_or("").to_string(),
-            public_data: match p {
-                Some(d) => {
-                    Some(ResponsePublicData {
-                        slogan: d.get_str("slogan").ok().map(String::from),
-                        image: d.get_str("image").ok().map(String::from)
-                    })
-                },
-                None => None
-            }
+            public_data: p.map(|d| ResponsePublicData {
+                slogan: d.get_str("slogan").ok().map(String::from),
+                image: d.get_str("image").ok().map(String::from)
+            })
         }
     }).collect()
 }
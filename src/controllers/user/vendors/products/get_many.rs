@@ -6,7 +6,7 @@ use mongodb::{
 };
 use crate::{
     app_error::AppError,
-    models::product::Product,
+    models::{product::Product, vendor::Vendor},
     helpers::results_per_page::results_per_page,
     dto::product::{ProductShortDb, ProductShortResponse}
 };
@@ -25,8 +25,9 @@ pub async fn route(
 ) -> Result<HttpResponse, AppError> {
     let vendor_id = ObjectId::parse_str(path.into_inner())
         .map_err(|_| AppError::invalid_input("Invalid vendor ID"))?;
+    Vendor::verify_active(&db, vendor_id).await?;
     let results_range = (10, 100);
-    let products: Vec<ProductShortDb> = Product::find_by_vendor(
+    let products: Vec<ProductShortDb> = Product::find_active_by_vendor(
         &db,
         vendor_id,
         ProductShortDb::projection(),
@@ -0,0 +1,163 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::{Method, header::{ORIGIN, REFERER}},
+    middleware::Next,
+    Error
+};
+use crate::{
+    app_error::AppError,
+    auth::bearer_token,
+    helpers::hash_token
+};
+
+pub const CSRF_COOKIE: &str = "csrf";
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+
+const AUTH_COOKIES: [&str; 3] = ["vendor", "admin", "customer"];
+
+/// Rejects state changing requests authenticated by cookie unless they come
+/// from an allowed origin or carry the double submit token. Requests without
+/// an auth cookie (login, API keys, webhooks) can't be forged and pass through.
+pub async fn csrf(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    if needs_check(&req) && !verified(&req) {
+        return Err(AppError::forbidden("Cross-site request rejected").into());
+    }
+
+    next.call(req).await
+}
+
+pub fn allowed_origin(origin: &str) -> bool {
+    let host = origin
+        .strip_prefix("https://")
+        .or_else(|| origin.strip_prefix("http://").filter(|_| cfg!(debug_assertions)));
+
+    match host {
+        Some(h) => {
+            h.ends_with(".inletsites.dev")
+                || (cfg!(debug_assertions) && (h.starts_with("localhost") || h.starts_with("127.0.0.1")))
+        },
+        None => false
+    }
+}
+
+fn needs_check(req: &ServiceRequest) -> bool {
+    if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return false;
+    }
+
+    if bearer_token(req.request()).is_some() {
+        return false;
+    }
+
+    AUTH_COOKIES.iter().any(|name| req.cookie(name).is_some())
+}
+
+fn verified(req: &ServiceRequest) -> bool {
+    let headers = req.headers();
+
+    if let Some(origin) = headers.get(ORIGIN).and_then(|h| h.to_str().ok()) {
+        return allowed_origin(origin);
+    }
+
+    if let Some(referer) = headers.get(REFERER).and_then(|h| h.to_str().ok()) {
+        return allowed_origin(&referer_origin(referer));
+    }
+
+    //No origin information (e.g. server side rendering), fall back to the
+    //double submit token
+    let header = headers.get(CSRF_HEADER).and_then(|h| h.to_str().ok());
+    match (req.cookie(CSRF_COOKIE), header) {
+        (Some(cookie), Some(header)) => tokens_match(cookie.value(), header),
+        _ => false
+    }
+}
+
+fn referer_origin(referer: &str) -> String {
+    match referer.find("://") {
+        Some(i) => {
+            let rest = &referer[i + 3..];
+            let end = rest.find('/').unwrap_or(rest.len());
+            format!("{}{}", &referer[..i + 3], &rest[..end])
+        },
+        None => String::new()
+    }
+}
+
+fn tokens_match(cookie: &str, header: &str) -> bool {
+    //Compare digests so the comparison time doesn't depend on the token
+    !cookie.is_empty() && hash_token(cookie) == hash_token(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test::TestRequest, cookie::Cookie};
+
+    //allowed_origin
+    #[test]
+    fn allows_subdomains() {
+        assert!(allowed_origin("https://vendor.inletsites.dev"));
+    }
+
+    #[test]
+    fn rejects_lookalike_domains() {
+        assert!(!allowed_origin("https://evilinletsites.dev"));
+        assert!(!allowed_origin("https://vendor.inletsites.dev.evil.com"));
+    }
+
+    //referer_origin
+    #[test]
+    fn strips_referer_path() {
+        let result = referer_origin("https://vendor.inletsites.dev/products/1?page=2");
+        assert_eq!(result, "https://vendor.inletsites.dev");
+    }
+
+    //needs_check
+    #[test]
+    fn skips_safe_methods() {
+        let req = TestRequest::get().cookie(Cookie::new("vendor", "token")).to_srv_request();
+        assert!(!needs_check(&req));
+    }
+
+    #[test]
+    fn skips_requests_without_auth_cookie() {
+        let req = TestRequest::post().to_srv_request();
+        assert!(!needs_check(&req));
+    }
+
+    #[test]
+    fn checks_cookie_mutations() {
+        let req = TestRequest::post().cookie(Cookie::new("vendor", "token")).to_srv_request();
+        assert!(needs_check(&req));
+    }
+
+    //verified
+    #[test]
+    fn rejects_foreign_origin() {
+        let req = TestRequest::post()
+            .insert_header((ORIGIN, "https://evil.com"))
+            .to_srv_request();
+        assert!(!verified(&req));
+    }
+
+    #[test]
+    fn accepts_matching_double_submit_token() {
+        let req = TestRequest::post()
+            .cookie(Cookie::new(CSRF_COOKIE, "abc"))
+            .insert_header((CSRF_HEADER, "abc"))
+            .to_srv_request();
+        assert!(verified(&req));
+    }
+
+    #[test]
+    fn rejects_missing_token() {
+        let req = TestRequest::post()
+            .cookie(Cookie::new(CSRF_COOKIE, "abc"))
+            .to_srv_request();
+        assert!(!verified(&req));
+    }
+}
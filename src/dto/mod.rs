@@ -1 +1,2 @@
 pub mod product;
+pub mod vendor;
@@ -1,6 +1,10 @@
 use serde::{Serialize, Deserialize};
 use mongodb::bson::{Document, DateTime, doc, oid::ObjectId};
-use crate::models::product::{Product, PurchaseOption};
+use std::collections::HashMap;
+use crate::models::{
+    product::{Product, PurchaseOption},
+    reservation::available
+};
 
 #[derive(Serialize, Deserialize)]
 pub struct ProductVendorDb {
@@ -181,13 +185,15 @@ pub struct PriceResponse {
     descriptor: String,
     price: i32,
     quantity: i32,
+    available: i32,
     shipping: i32,
     images: Vec<String>,
     purchase_option: PurOptDb
 }
 
-impl From<ProductDb> for ProductResponse {
-    fn from(p: ProductDb) -> Self {
+impl ProductResponse {
+    /// `reserved` is the quantity held by reservations for each price.
+    pub fn new(p: ProductDb, reserved: &HashMap<ObjectId, i32>) -> Self {
         ProductResponse {
             id: p._id.to_string(),
             vendor: p.vendor.to_string(),
@@ -201,6 +207,7 @@ impl From<ProductDb> for ProductResponse {
                     descriptor: pr.descriptor,
                     price: pr.price,
                     quantity: pr.quantity,
+                    available: available(pr.quantity, reserved.get(&pr._id)),
                     shipping: pr.shipping,
                     images: pr.images,
                     purchase_option: pr.purchase_option
@@ -211,6 +218,14 @@ impl From<ProductDb> for ProductResponse {
 }
 
 impl ProductDb {
+    pub fn vendor(&self) -> ObjectId {
+        self.vendor
+    }
+
+    pub fn price_ids(&self) -> Vec<ObjectId> {
+        self.prices.iter().map(|p| p._id).collect()
+    }
+
     pub fn projection() -> Document {
         doc!{
             "_id": 1,
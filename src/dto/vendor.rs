@@ -0,0 +1,10 @@
+use serde::Deserialize;
+
+/// Body for creating a vendor from the admin and operator routes.
+#[derive(Deserialize)]
+pub struct NewVendor {
+    pub email: String,
+    pub owner: String,
+    pub store: String,
+    pub url: Option<String>
+}
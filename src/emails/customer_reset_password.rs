@@ -0,0 +1,17 @@
+pub fn customer_reset_password(name: String, id: String, token: String) -> String {
+    format!(
+        r#"
+<p>Hello {name},</p>
+
+<p>We have received a request to reset the password for your Inlet.Shop account. To do this, use the link below. The link can only be used once and expires in one hour.</p>
+
+<p>If you did not make this request, then you can safely ignore this email.</p>
+
+<a href="https://inlet.shop/password/{id}/{token}">
+    inlet.shop/password/{id}/{token}
+</a>
+
+<p>-Inlet Sites</p>
+"#
+    )
+}
@@ -0,0 +1,31 @@
+pub fn confirm_email_change(name: String, id: String, token: String) -> String {
+    format!(
+        r#"
+<p>Hello {name},</p>
+
+<p>We have received a request to change the email address for your Inlet.Shop account to this address. To confirm the change, use the link below. The link can only be used once and expires in 24 hours.</p>
+
+<p>If you did not make this request, then you can safely ignore this email.</p>
+
+<a href="https://vendor.inlet.shop/email/{id}/{token}">
+    vendor.inlet.shop/email/{id}/{token}
+</a>
+
+<p>-Inlet Sites</p>
+"#
+    )
+}
+
+pub fn email_changed(name: String, new_email: String) -> String {
+    format!(
+        r#"
+<p>Hello {name},</p>
+
+<p>The email address for your Inlet.Shop account has been changed to {new_email}. You will need to use the new address to log in.</p>
+
+<p>If you did not make this change, contact support@inletsites.dev immediately.</p>
+
+<p>-Inlet Sites</p>
+"#
+    )
+}
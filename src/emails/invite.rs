@@ -0,0 +1,17 @@
+pub fn invite(name: String, store: String, id: String, token: String) -> String {
+    format!(
+        r#"
+<p>Hello {name},</p>
+
+<p>An account has been created for {store} on Inlet.Shop. To get started, use the link below to create your password.</p>
+
+<p>This link can only be used once and expires in 7 days.</p>
+
+<a href="https://vendor.inlet.shop/password/create/{id}/{token}">
+    vendor.inlet.shop/password/create/{id}/{token}
+</a>
+
+<p>-Inlet Sites</p>
+"#
+    )
+}
@@ -0,0 +1,17 @@
+pub fn login_link(name: String, id: String, token: String) -> String {
+    format!(
+        r#"
+<p>Hello {name},</p>
+
+<p>Use the link below to sign in to Inlet.Shop. The link can only be used once and expires in 15 minutes.</p>
+
+<p>If you did not request this link, then you can safely ignore this email.</p>
+
+<a href="https://vendor.inlet.shop/login/link/{id}/{token}">
+    vendor.inlet.shop/login/link/{id}/{token}
+</a>
+
+<p>-Inlet Sites</p>
+"#
+    )
+}
@@ -1,3 +1,19 @@
+mod send;
 mod reset_password;
+mod invite;
+mod email_change;
+mod login_link;
+mod staff_invite;
+mod staff_reset_password;
+mod customer_reset_password;
+mod order;
 
+pub use send::{send_email, send_email_with_retry, send_in_background};
 pub use reset_password::reset_password;
+pub use invite::invite;
+pub use email_change::{confirm_email_change, email_changed};
+pub use login_link::login_link;
+pub use staff_invite::staff_invite;
+pub use staff_reset_password::staff_reset_password;
+pub use customer_reset_password::customer_reset_password;
+pub use order::{new_order, order_receipt, order_refunded};
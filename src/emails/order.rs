@@ -0,0 +1,166 @@
+use crate::models::order::Order;
+
+pub fn new_order(owner: &str, order: &Order) -> String {
+    format!(
+        r#"
+<p>Hello {owner},</p>
+
+<p>You have a new order from {customer}. It has been paid and is ready to be fulfilled.</p>
+
+{details}
+
+<p>You can manage the order from your dashboard at <a href="https://vendor.inlet.shop/orders/{id}">vendor.inlet.shop/orders/{id}</a>.</p>
+
+<p>You are receiving this because new order emails are turned on for your store.</p>
+
+<p>-Inlet Sites</p>
+"#,
+        owner = escape(owner),
+        customer = escape(&order.contact.name),
+        details = order_details(order),
+        id = order._id
+    )
+}
+
+pub fn order_receipt(store: &str, order: &Order) -> String {
+    format!(
+        r#"
+<p>Hello {customer},</p>
+
+<p>Thank you for your order from {store}. Your payment has been received and the store has been notified.</p>
+
+{details}
+
+<p>Order number: {id}</p>
+
+<p>-Inlet Sites</p>
+"#,
+        customer = escape(&order.contact.name),
+        store = escape(store),
+        details = order_details(order),
+        id = order._id
+    )
+}
+
+pub fn order_refunded(name: &str, store: &str, amount: i64, payment: &str) -> String {
+    format!(
+        r#"
+<p>Hello {name},</p>
+
+<p>{store} has refunded {amount} for your purchase (payment {payment}). Refunds usually take 5 to 10 business days to show on your statement.</p>
+
+<p>-Inlet Sites</p>
+"#,
+        name = escape(name),
+        store = escape(store),
+        amount = money(amount),
+        payment = escape(payment)
+    )
+}
+
+fn order_details(order: &Order) -> String {
+    let mut rows = String::new();
+    for item in &order.items {
+        rows.push_str(&format!(
+            "<tr><td>{} ({})</td><td>{}</td><td>{}</td></tr>\n",
+            escape(&item.name),
+            escape(&item.descriptor),
+            item.quantity,
+            money(item.subtotal())
+        ));
+    }
+
+    let address = match &order.shipping_address {
+        Some(a) => {
+            let mut lines = vec![escape(&a.name), escape(&a.line1)];
+            if let Some(l) = &a.line2 {
+                lines.push(escape(l));
+            }
+            lines.push(format!("{}, {} {}", escape(&a.city), escape(&a.state), escape(&a.postal_code)));
+            lines.push(escape(&a.country));
+            format!("<p>Shipping to:<br>\n{}</p>", lines.join("<br>\n"))
+        },
+        None => String::from("<p>No shipping, items will be picked up from the store.</p>")
+    };
+
+    format!(
+        r#"<table>
+<tr><th>Item</th><th>Quantity</th><th>Price</th></tr>
+{rows}</table>
+
+<p>Subtotal: {subtotal}<br>
+Shipping: {shipping}<br>
+Total: {total}</p>
+
+{address}"#,
+        subtotal = money(order.subtotal),
+        shipping = money(order.shipping),
+        total = money(order.total)
+    )
+}
+
+fn money(cents: i64) -> String {
+    format!("${}.{:02}", cents / 100, cents % 100)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{checkout::LineItem, order::OrderBuilder};
+
+    fn order() -> Order {
+        Order::builder()
+            .items(vec![LineItem {
+                name: String::from("Bait <Live>"),
+                unit_price: 1200,
+                ..OrderBuilder::line_item(2)
+            }])
+            .total(2400)
+            .contact("Jane Doe", "jane@example.com")
+            .build()
+    }
+
+    //new_order
+    #[test]
+    fn lists_items_and_totals() {
+        let html = new_order("Lee", &order());
+        assert!(html.contains("Bait &lt;Live&gt; (Dozen)"));
+        assert!(html.contains("Total: $24.00"));
+        assert!(html.contains("picked up"));
+    }
+
+    //order_receipt
+    #[test]
+    fn receipt_names_store() {
+        let html = order_receipt("Inlet Bait & Tackle", &order());
+        assert!(html.contains("Inlet Bait &amp; Tackle"));
+        assert!(html.contains("Hello Jane Doe"));
+    }
+
+    //money
+    #[test]
+    fn formats_cents() {
+        assert_eq!(money(1205), "$12.05");
+        assert_eq!(money(0), "$0.00");
+    }
+
+    //escape
+    #[test]
+    fn escapes_html() {
+        assert_eq!(escape("<b>Bait & Tackle</b>"), "&lt;b&gt;Bait &amp; Tackle&lt;/b&gt;");
+    }
+
+    //order_refunded
+    #[test]
+    fn refund_shows_amount() {
+        let html = order_refunded("Jane", "Bait & Tackle", 1250, "pi_123");
+        assert!(html.contains("Bait &amp; Tackle has refunded $12.50"));
+    }
+}
@@ -3,7 +3,7 @@ pub fn reset_password(name: String, id: String, token: String) -> String {
         r#"
 <p>Hello {name},</p>
 
-<p>We have received a request to reset your password. To do this, simply use the link below and enter your email address.</p>
+<p>We have received a request to reset your password. To do this, simply use the link below and enter your email address. The link can only be used once and expires in one hour.</p>
 
 <p>If you did not make this request, then you can safely ignore this email.</p>
 
@@ -0,0 +1,99 @@
+use reqwest::Client;
+use serde_json::json;
+use std::time::Duration;
+use crate::app_error::AppError;
+
+const MAX_ATTEMPTS: u32 = 4;
+
+pub async fn send_email(
+    address: &str,
+    name: &str,
+    subject: &str,
+    html: String
+) -> Result<(), AppError> {
+    let client = Client::new();
+
+    let zepto_token = std::env::var("ZEPTO_TOKEN")
+        .map_err(|_| AppError::InternalError)?;
+
+    let body = json!({
+        "from": {
+            "address": "support@inletsites.dev"
+        },
+        "to": [{
+            "email_address": {
+                "address": address,
+                "name": name
+            }
+        }],
+        "subject": subject,
+        "htmlbody": html
+    });
+
+    let response = client
+        .post("https://zeptomail.zoho.com/v1.1/email")
+        .header("Accept", "application/json")
+        .header("Content-Type", "application/json")
+        .header("Authorization", zepto_token)
+        .json(&body)
+        .send()
+        .await?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(AppError::InternalError)
+    }
+}
+
+/// Sends an email from a background task and logs any failure. Routes that
+/// take an email address use this so the response, and how long it takes, is
+/// the same whether or not the address has an account.
+pub fn send_in_background<F>(what: &'static str, send: F)
+where
+    F: Future<Output = Result<(), AppError>> + Send + 'static
+{
+    tokio::spawn(async move {
+        if let Err(e) = send.await {
+            eprintln!("Failed to send {} email: {:?}", what, e);
+        }
+    });
+}
+
+/// Sends an email, retrying failures with a doubling delay. For emails sent
+/// in the background where nobody is waiting to see an error.
+pub async fn send_email_with_retry(
+    address: &str,
+    name: &str,
+    subject: &str,
+    html: String
+) -> Result<(), AppError> {
+    let mut attempt = 1;
+    loop {
+        match send_email(address, name, subject, html.clone()).await {
+            Ok(_) => return Ok(()),
+            Err(e) if attempt >= MAX_ATTEMPTS => return Err(e),
+            Err(_) => ()
+        }
+
+        tokio::time::sleep(retry_delay(attempt)).await;
+        attempt += 1;
+    }
+}
+
+fn retry_delay(attempt: u32) -> Duration {
+    Duration::from_secs(2u64.pow(attempt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //retry_delay
+    #[test]
+    fn delay_doubles() {
+        assert_eq!(retry_delay(1), Duration::from_secs(2));
+        assert_eq!(retry_delay(2), Duration::from_secs(4));
+        assert_eq!(retry_delay(3), Duration::from_secs(8));
+    }
+}
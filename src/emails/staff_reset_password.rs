@@ -0,0 +1,17 @@
+pub fn staff_reset_password(name: String, store: String, id: String, token: String) -> String {
+    format!(
+        r#"
+<p>Hello {name},</p>
+
+<p>We have received a request to reset the password for your staff login at {store} on Inlet.Shop. To do this, use the link below. The link can only be used once and expires in one hour.</p>
+
+<p>If you did not make this request, then you can safely ignore this email.</p>
+
+<a href="https://vendor.inlet.shop/staff/reset/{id}/{token}">
+    vendor.inlet.shop/staff/reset/{id}/{token}
+</a>
+
+<p>-Inlet Sites</p>
+"#
+    )
+}
@@ -0,0 +1,22 @@
+use actix_web::cookie::{Cookie, SameSite};
+
+pub fn auth_cookie(name: &'static str, value: String) -> Cookie<'static> {
+    if cfg!(debug_assertions){
+        Cookie::build(name, value)
+            .path("/")
+            .http_only(true)
+            .finish()
+    } else {
+        Cookie::build(name, value)
+            .domain(".inletsites.dev")
+            .path("/")
+            .same_site(SameSite::None)
+            .http_only(true)
+            .secure(true)
+            .finish()
+    }
+}
+
+pub fn removal_cookie(name: &'static str) -> Cookie<'static> {
+    auth_cookie(name, String::new())
+}
@@ -0,0 +1,103 @@
+use std::{net::IpAddr, sync::OnceLock};
+use actix_web::{HttpRequest, http::header::USER_AGENT};
+
+/// Address of the client making the request. `X-Forwarded-For` is only read
+/// when the connection comes from a proxy listed in `TRUSTED_PROXIES`, since
+/// anyone can send the header.
+pub fn client_ip(req: &HttpRequest) -> Option<String> {
+    let forwarded_for = req.headers()
+        .get("X-Forwarded-For")
+        .and_then(|h| h.to_str().ok());
+
+    resolve_ip(req.peer_addr().map(|a| a.ip()), forwarded_for, trusted_proxies())
+        .map(|ip| ip.to_string())
+}
+
+pub fn user_agent(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get(USER_AGENT)
+        .and_then(|h| h.to_str().ok())
+        .map(String::from)
+}
+
+fn trusted_proxies() -> &'static [IpAddr] {
+    static PROXIES: OnceLock<Vec<IpAddr>> = OnceLock::new();
+    PROXIES.get_or_init(|| {
+        std::env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|p| p.trim().parse().ok())
+            .collect()
+    })
+}
+
+/// Walks `X-Forwarded-For` from the right, skipping our own proxies. The
+/// first address that isn't a trusted proxy is the client, anything to its
+/// left was written by the client and is ignored.
+fn resolve_ip(peer: Option<IpAddr>, forwarded_for: Option<&str>, trusted: &[IpAddr]) -> Option<IpAddr> {
+    let mut ip = peer?;
+    if !trusted.contains(&ip) {
+        return Some(ip);
+    }
+
+    for hop in forwarded_for.unwrap_or("").rsplit(',') {
+        match hop.trim().parse::<IpAddr>() {
+            Ok(hop) => {
+                ip = hop;
+                if !trusted.contains(&hop) {
+                    break;
+                }
+            },
+            Err(_) => break
+        }
+    }
+
+    Some(ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+    use crate::models::throttle::ip_key;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    //client_ip
+    #[test]
+    fn spoofed_header_keeps_throttle_key() {
+        let plain = TestRequest::default()
+            .peer_addr("203.0.113.7:5000".parse().unwrap())
+            .to_http_request();
+        let spoofed = TestRequest::default()
+            .peer_addr("203.0.113.7:5000".parse().unwrap())
+            .insert_header(("X-Forwarded-For", "198.51.100.1"))
+            .insert_header(("Forwarded", "for=198.51.100.2"))
+            .to_http_request();
+
+        assert_eq!(ip_key("login", client_ip(&spoofed)), ip_key("login", client_ip(&plain)));
+        assert_eq!(client_ip(&spoofed).unwrap(), "203.0.113.7");
+    }
+
+    //resolve_ip
+    #[test]
+    fn ignores_header_from_untrusted_peer() {
+        let result = resolve_ip(Some(ip("203.0.113.7")), Some("198.51.100.1"), &[ip("127.0.0.1")]);
+        assert_eq!(result, Some(ip("203.0.113.7")));
+    }
+
+    #[test]
+    fn takes_last_untrusted_hop() {
+        let trusted = [ip("127.0.0.1"), ip("10.0.0.2")];
+        let result = resolve_ip(Some(ip("127.0.0.1")), Some("198.51.100.1, 203.0.113.7, 10.0.0.2"), &trusted);
+        assert_eq!(result, Some(ip("203.0.113.7")));
+    }
+
+    #[test]
+    fn falls_back_to_proxy_without_header() {
+        let result = resolve_ip(Some(ip("127.0.0.1")), None, &[ip("127.0.0.1")]);
+        assert_eq!(result, Some(ip("127.0.0.1")));
+    }
+}
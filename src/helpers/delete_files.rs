@@ -13,10 +13,7 @@ pub fn delete_files(urls: Vec<String>) {
         let full_path = format!("{}srv/{}", home, u);
         match fs::remove_file(full_path) {
             Ok(_) => (),
-            Err(_) => {
-                eprintln!("Failed to remove file: {}", u);
-                ()
-            }
+            Err(_) => eprintln!("Failed to remove file: {}", u)
         };
     }
 }
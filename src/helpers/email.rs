@@ -0,0 +1,80 @@
+use futures::stream::TryStreamExt;
+use mongodb::{
+    bson::doc,
+    options::{Collation, CollationStrength},
+    Collection,
+    IndexModel
+};
+use crate::app_error::AppError;
+
+/// Trims and lowercases an email address, rejecting anything that isn't
+/// shaped like one.
+pub fn normalize_email(email: &str) -> Result<String, AppError> {
+    let email = email.trim().to_lowercase();
+
+    let valid = match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !domain.contains('@')
+                && !email.contains(char::is_whitespace)
+        },
+        None => false
+    };
+
+    match valid {
+        true => Ok(email),
+        false => Err(AppError::invalid_input("Invalid email address"))
+    }
+}
+
+/// Compares emails without case, for unique indexes and the lookups that
+/// need to use them.
+pub fn email_collation() -> Collation {
+    Collation::builder()
+        .locale("en")
+        .strength(CollationStrength::Secondary)
+        .build()
+}
+
+/// Drops an `email` index built before it had a collation. An index can't be
+/// changed in place, so `create_indexes` builds it again afterwards.
+pub async fn drop_uncollated_email_index<T: Send + Sync>(collection: &Collection<T>) -> Result<(), AppError> {
+    //Listing fails before the collection exists, so there's nothing to do
+    let indexes: Vec<IndexModel> = match collection.list_indexes().await {
+        Ok(cursor) => cursor.try_collect().await?,
+        Err(_) => return Ok(())
+    };
+
+    let outdated = indexes.iter().any(|i| {
+        i.keys == doc!{"email": 1} && i.options.as_ref().and_then(|o| o.collation.as_ref()).is_none()
+    });
+    if outdated {
+        collection.drop_index("email_1").await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lowercases_and_trims() {
+        let result = normalize_email("  John@InletSites.dev ").unwrap();
+        assert_eq!(result, "john@inletsites.dev");
+    }
+
+    #[test]
+    fn rejects_missing_domain() {
+        assert!(normalize_email("john@").is_err());
+        assert!(normalize_email("john@inletsites").is_err());
+    }
+
+    #[test]
+    fn rejects_multiple_at_signs() {
+        assert!(normalize_email("john@doe@inletsites.dev").is_err());
+    }
+}
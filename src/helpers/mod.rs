@@ -1,7 +1,20 @@
 pub mod results_per_page;
 pub mod shrink_and_write_image;
 pub mod delete_files;
+pub mod token;
+pub mod client_info;
+pub mod totp;
+pub mod auth_cookie;
+pub mod slugify;
+pub mod email;
+pub mod password_policy;
+pub mod stripe;
 
 pub use results_per_page::results_per_page;
 pub use shrink_and_write_image::shrink_and_write_image;
 pub use delete_files::delete_files;
+pub use token::{generate_token, hash_token};
+pub use client_info::{client_ip, user_agent};
+pub use auth_cookie::{auth_cookie, removal_cookie};
+pub use slugify::slugify;
+pub use email::{normalize_email, email_collation, drop_uncollated_email_index};
@@ -0,0 +1,89 @@
+use std::{collections::HashSet, sync::OnceLock};
+use crate::app_error::AppError;
+
+pub const MIN_LENGTH: usize = 10;
+pub const MAX_LENGTH: usize = 128;
+
+const COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+/// Checks a new password against the password rules. Does not check that it
+/// matches the confirmation.
+pub fn check_password(password: &str) -> Result<(), AppError> {
+    let length = password.chars().count();
+    if length < MIN_LENGTH {
+        return Err(AppError::invalid_input(&format!("Password must contain at least {} characters", MIN_LENGTH)));
+    }
+
+    if length > MAX_LENGTH {
+        return Err(AppError::invalid_input(&format!("Password must contain at most {} characters", MAX_LENGTH)));
+    }
+
+    if common_passwords().contains(password.to_lowercase().as_str()) {
+        return Err(AppError::invalid_input("Password is too common, choose something harder to guess"));
+    }
+
+    Ok(())
+}
+
+/// The bundled list plus, if `COMMON_PASSWORDS_FILE` is set, a larger list
+/// such as a top 10k. A file that can't be read is logged and skipped.
+fn common_passwords() -> &'static HashSet<String> {
+    static LIST: OnceLock<HashSet<String>> = OnceLock::new();
+    LIST.get_or_init(|| {
+        let extra = std::env::var("COMMON_PASSWORDS_FILE").ok().and_then(|path| {
+            match std::fs::read_to_string(&path) {
+                Ok(text) => Some(text),
+                Err(e) => {
+                    eprintln!("Failed to read common passwords from {}: {:?}", path, e);
+                    None
+                }
+            }
+        });
+        parse_lists(COMMON_PASSWORDS, extra.as_deref())
+    })
+}
+
+/// Only entries long enough to pass the length check are kept, the rest
+/// could never match.
+fn parse_lists(bundled: &str, extra: Option<&str>) -> HashSet<String> {
+    bundled.lines()
+        .chain(extra.unwrap_or_default().lines())
+        .map(|l| l.trim().to_lowercase())
+        .filter(|l| l.chars().count() >= MIN_LENGTH)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_short_password() {
+        assert!(matches!(check_password("short"), Err(AppError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn rejects_long_password() {
+        let password = "a".repeat(MAX_LENGTH + 1);
+        assert!(matches!(check_password(&password), Err(AppError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn rejects_common_password_any_case() {
+        assert!(matches!(check_password("PassWord123"), Err(AppError::InvalidInput(_))));
+    }
+
+    //parse_lists
+    #[test]
+    fn merges_extra_list() {
+        let result = parse_lists("password123\n", Some("  Sunshine2024\r\nshort\n"));
+        assert!(result.contains("password123"));
+        assert!(result.contains("sunshine2024"));
+        assert!(!result.contains("short"));
+    }
+
+    #[test]
+    fn accepts_uncommon_password() {
+        assert!(check_password("Tidewater-Bait-42").is_ok());
+    }
+}
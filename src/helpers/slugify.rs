@@ -0,0 +1,33 @@
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+
+    for c in text.trim().to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.ends_with('-') && !slug.is_empty() {
+            slug.push('-');
+        }
+    }
+
+    slug.trim_end_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_spaces() {
+        assert_eq!(slugify("Inlet Sites"), "inlet-sites");
+    }
+
+    #[test]
+    fn removes_punctuation() {
+        assert_eq!(slugify("  Joe's Bait & Tackle!  "), "joes-bait-tackle");
+    }
+
+    #[test]
+    fn collapses_separators() {
+        assert_eq!(slugify("A -- B__C"), "a-b-c");
+    }
+}
@@ -0,0 +1,115 @@
+use std::sync::OnceLock;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use crate::app_error::AppError;
+
+const DEFAULT_APPLICATION_FEE_BPS: i64 = 500;
+const SIGNATURE_TOLERANCE_SECS: i64 = 300;
+
+/// Platform fee taken from each checkout, in basis points of the item total.
+pub fn application_fee_bps() -> i64 {
+    static BPS: OnceLock<i64> = OnceLock::new();
+    *BPS.get_or_init(|| {
+        std::env::var("STRIPE_APPLICATION_FEE_BPS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v| (0..=10_000).contains(v))
+            .unwrap_or(DEFAULT_APPLICATION_FEE_BPS)
+    })
+}
+
+pub fn application_fee(amount: i64, bps: i64) -> i64 {
+    amount * bps / 10_000
+}
+
+/// Checks a `Stripe-Signature` header against the raw request body.
+/// `secrets` is a comma separated list so the platform and Connect endpoint
+/// secrets can both be accepted.
+pub fn verify_signature(payload: &[u8], header: &str, secrets: &str, now: i64) -> Result<(), AppError> {
+    let invalid = || AppError::invalid_input("Invalid Stripe signature");
+
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", t)) => timestamp = t.parse::<i64>().ok(),
+            Some(("v1", s)) => signatures.extend(hex::decode(s).ok()),
+            _ => ()
+        }
+    }
+
+    let timestamp = timestamp.ok_or_else(invalid)?;
+    if (now - timestamp).abs() > SIGNATURE_TOLERANCE_SECS {
+        return Err(invalid());
+    }
+
+    for secret in secrets.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .map_err(|_| AppError::InternalError)?;
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(payload);
+
+        if signatures.iter().any(|s| mac.clone().verify_slice(s).is_ok()) {
+            return Ok(());
+        }
+    }
+
+    Err(invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(payload: &[u8], secret: &str, timestamp: i64) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("{}.", timestamp).as_bytes());
+        mac.update(payload);
+        format!("t={},v1={}", timestamp, hex::encode(mac.finalize().into_bytes()))
+    }
+
+    //verify_signature
+    #[test]
+    fn accepts_valid_signature() {
+        let header = sign(b"{}", "whsec_test", 1_700_000_000);
+        assert!(verify_signature(b"{}", &header, "whsec_test", 1_700_000_010).is_ok());
+    }
+
+    #[test]
+    fn accepts_any_configured_secret() {
+        let header = sign(b"{}", "whsec_connect", 1_700_000_000);
+        assert!(verify_signature(b"{}", &header, "whsec_test, whsec_connect", 1_700_000_000).is_ok());
+    }
+
+    #[test]
+    fn rejects_modified_payload() {
+        let header = sign(b"{}", "whsec_test", 1_700_000_000);
+        let result = verify_signature(b"{\"a\":1}", &header, "whsec_test", 1_700_000_000);
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn rejects_old_timestamp() {
+        let header = sign(b"{}", "whsec_test", 1_700_000_000);
+        let result = verify_signature(b"{}", &header, "whsec_test", 1_700_000_301);
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn rejects_missing_signature() {
+        let result = verify_signature(b"{}", "t=1700000000", "whsec_test", 1_700_000_000);
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    //application_fee
+    #[test]
+    fn takes_percentage_of_amount() {
+        assert_eq!(application_fee(10_000, 500), 500);
+    }
+
+    #[test]
+    fn rounds_down() {
+        assert_eq!(application_fee(999, 500), 49);
+    }
+}
@@ -0,0 +1,34 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use sha2::{Sha256, Digest};
+
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_unique() {
+        assert_ne!(generate_token(), generate_token());
+    }
+
+    #[test]
+    fn token_has_full_length() {
+        assert_eq!(generate_token().len(), 64);
+    }
+
+    #[test]
+    fn hash_is_deterministic() {
+        let token = generate_token();
+        assert_eq!(hash_token(&token), hash_token(&token));
+        assert_ne!(hash_token(&token), token);
+    }
+}
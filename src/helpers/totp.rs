@@ -0,0 +1,129 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const STEP_SECS: u64 = 30;
+const DIGITS: u32 = 6;
+const ISSUER: &str = "Inlet.Shop";
+
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 20];
+    OsRng.fill_bytes(&mut bytes);
+    BASE32_NOPAD.encode(&bytes)
+}
+
+pub fn otpauth_uri(secret: &str, account: &str) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{account}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={DIGITS}&period={STEP_SECS}",
+        issuer = percent_encode(ISSUER),
+        account = percent_encode(account)
+    )
+}
+
+/// Encodes everything but the RFC 3986 unreserved characters, so a `:`, `@`,
+/// `&`, `?`, `#` or `/` can't change how the label or query is parsed.
+fn percent_encode(value: &str) -> String {
+    value.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b)
+        })
+        .collect()
+}
+
+pub fn current_step() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / STEP_SECS)
+        .unwrap_or(0)
+}
+
+/// Returns the time step the code matched, allowing one step of clock drift
+/// in either direction.
+pub fn verify(secret: &str, code: &str, step: u64) -> Option<u64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    [step.saturating_sub(1), step, step + 1]
+        .into_iter()
+        .find(|s| code_at(secret, *s).as_deref() == Some(code))
+}
+
+fn code_at(secret: &str, step: u64) -> Option<String> {
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    let mut mac = Hmac::<Sha1>::new_from_slice(&key).ok()?;
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3]
+    ]);
+
+    Some(format!("{:0width$}", binary % 10u32.pow(DIGITS), width = DIGITS as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //RFC 6238 test secret "12345678901234567890"
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn matches_rfc_vector() {
+        let result = code_at(RFC_SECRET, 59 / STEP_SECS);
+        assert_eq!(result.as_deref(), Some("287082"));
+    }
+
+    #[test]
+    fn accepts_adjacent_step() {
+        let step = 1111111109 / STEP_SECS;
+        let code = code_at(RFC_SECRET, step - 1).unwrap();
+
+        assert_eq!(verify(RFC_SECRET, &code, step), Some(step - 1));
+    }
+
+    #[test]
+    fn rejects_wrong_code() {
+        let step = 1111111109 / STEP_SECS;
+        let code = code_at(RFC_SECRET, step + 5).unwrap();
+
+        assert_eq!(verify(RFC_SECRET, &code, step), None);
+    }
+
+    #[test]
+    fn rejects_malformed_code() {
+        assert_eq!(verify(RFC_SECRET, "12a456", 1), None);
+    }
+
+    //otpauth_uri
+    #[test]
+    fn encodes_label() {
+        let result = otpauth_uri("ABC", "jo hn@shop.com");
+        assert!(result.starts_with("otpauth://totp/Inlet.Shop:jo%20hn%40shop.com?secret=ABC&issuer=Inlet.Shop&"));
+    }
+
+    #[test]
+    fn encodes_reserved_characters() {
+        assert_eq!(percent_encode("a:b&c?d#e/f"), "a%3Ab%26c%3Fd%23e%2Ff");
+    }
+
+    #[test]
+    fn encodes_non_ascii() {
+        assert_eq!(percent_encode("é"), "%C3%A9");
+    }
+
+    #[test]
+    fn secret_decodes() {
+        let secret = generate_secret();
+        assert_eq!(BASE32_NOPAD.decode(secret.as_bytes()).unwrap().len(), 20);
+    }
+}
@@ -1,4 +1,4 @@
-use actix_web::{HttpServer, web, App};
+use actix_web::{HttpServer, web, App, middleware};
 use actix_cors::Cors;
 use mongodb::{Client, Database};
 use crate::app_error::AppError;
@@ -8,9 +8,11 @@ mod models;
 mod controllers;
 mod app_error;
 mod auth;
+mod csrf;
 mod emails;
 mod dto;
 mod helpers;
+mod stripe;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -21,6 +23,9 @@ async fn main() -> std::io::Result<()> {
         "mongodb://127.0.0.1:27017".to_string()
     };
     let db = connect_db(&mongo_uri, "inletshop").await;
+    migrate(&db).await.expect("Failed to migrate database");
+    create_indexes(&db).await.expect("Failed to create database indexes");
+    let stripe = web::Data::new(stripe::StripeClient::from_env());
 
     HttpServer::new (move || {
         let cors = if app_env == "development" {
@@ -28,7 +33,7 @@ async fn main() -> std::io::Result<()> {
         } else {
             Cors::default()
                 .allowed_origin_fn(|origin, _req| {
-                    origin.as_bytes().ends_with(b".inletsites.dev")
+                    csrf::allowed_origin(origin.to_str().unwrap_or(""))
                 })
                 .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
                 .allow_any_header()
@@ -36,8 +41,10 @@ async fn main() -> std::io::Result<()> {
         };
 
         App::new()
+            .wrap(middleware::from_fn(csrf::csrf))
             .wrap(cors)
             .app_data(web::Data::new(db.clone()))
+            .app_data(stripe.clone())
             .app_data(
                 web::JsonConfig::default().error_handler(|err, _req| {
                     AppError::JsonDeserializationError(err.to_string()).into()
@@ -46,6 +53,9 @@ async fn main() -> std::io::Result<()> {
             .configure(routes::other::config)
             .configure(routes::user::config)
             .configure(routes::vendor::config)
+            .configure(routes::operator::config)
+            .configure(routes::admin::config)
+            .configure(routes::stripe::config)
     })
         .bind(("0.0.0.0", 8001))?
         .run()
@@ -56,3 +66,50 @@ async fn connect_db(uri: &str, db_name: &str) -> Database {
     let client = Client::with_uri_str(uri).await.expect("Failed to connect to database");
     client.database(db_name)
 }
+
+/// Data fixes for older documents. Each one is safe to run on every start.
+async fn migrate(db: &Database) -> Result<(), AppError> {
+    models::token::Token::migrate(db).await?;
+    models::staff::Staff::migrate(db).await?;
+    models::customer::Customer::migrate(db).await?;
+    Ok(())
+}
+
+async fn create_indexes(db: &Database) -> Result<(), AppError> {
+    allow_duplicates("Vendor", models::vendor::Vendor::create_indexes(db).await)?;
+    allow_duplicates("Session", models::session::Session::create_indexes(db).await)?;
+    allow_duplicates("Throttle", models::throttle::Throttle::create_indexes(db).await)?;
+    allow_duplicates("LoginChallenge", models::login_challenge::LoginChallenge::create_indexes(db).await)?;
+    allow_duplicates("Token", models::token::Token::create_indexes(db).await)?;
+    allow_duplicates("ApiKey", models::api_key::ApiKey::create_indexes(db).await)?;
+    allow_duplicates("Admin", models::admin::Admin::create_indexes(db).await)?;
+    allow_duplicates("Staff", models::staff::Staff::create_indexes(db).await)?;
+    allow_duplicates("Customer", models::customer::Customer::create_indexes(db).await)?;
+    allow_duplicates("AuditEntry", models::audit::AuditEntry::create_indexes(db).await)?;
+    allow_duplicates("Checkout", models::checkout::Checkout::create_indexes(db).await)?;
+    allow_duplicates("Order", models::order::Order::create_indexes(db).await)?;
+    allow_duplicates("StripeEvent", models::stripe_event::StripeEvent::create_indexes(db).await)?;
+    allow_duplicates("Reservation", models::reservation::Reservation::create_indexes(db).await)?;
+    allow_duplicates("Refund", models::refund::Refund::create_indexes(db).await)?;
+    Ok(())
+}
+
+/// Unique indexes can't be built while the collection already holds
+/// duplicates. That shouldn't keep the server down, so it's logged and the
+/// other collections still get their indexes. The duplicates need merging by
+/// hand, then a restart builds the rest.
+fn allow_duplicates(model: &str, result: Result<(), AppError>) -> Result<(), AppError> {
+    match result {
+        Err(AppError::Database(e)) => {
+            let message = e.to_string();
+            match AppError::from_write(e, &message) {
+                AppError::Conflict(_) => {
+                    eprintln!("Some {} indexes were not created, existing documents have duplicate keys: {}", model, message);
+                    Ok(())
+                },
+                e => Err(e)
+            }
+        },
+        result => result
+    }
+}
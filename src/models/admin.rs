@@ -0,0 +1,75 @@
+use serde::{Serialize, Deserialize};
+use mongodb::{
+    bson::{oid::ObjectId, DateTime, doc},
+    options::IndexOptions,
+    Database,
+    IndexModel
+};
+use crate::app_error::AppError;
+
+#[derive(Serialize, Deserialize)]
+pub struct Admin {
+    pub _id: ObjectId,
+    pub email: String,
+    pub name: String,
+    pub pass_hash: String,
+    pub created_at: DateTime
+}
+
+#[derive(Serialize)]
+pub struct ResponseAdmin {
+    id: String,
+    email: String,
+    name: String
+}
+
+impl Admin {
+    pub async fn insert(&self, db: &Database) -> Result<(), AppError> {
+        match db.collection::<Admin>("admins").insert_one(self).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(AppError::from_write(e, "An admin with this email already exists"))
+        }
+    }
+
+    pub async fn find_by_id(db: &Database, admin_id: ObjectId) -> Result<Admin, AppError> {
+        match db.collection::<Admin>("admins").find_one(doc!{"_id": admin_id}).await {
+            Ok(Some(a)) => Ok(a),
+            Ok(None) => Err(AppError::not_found("Admin with this ID not found")),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    pub async fn find_by_email(db: &Database, email: &str) -> Result<Admin, AppError> {
+        match db.collection::<Admin>("admins").find_one(doc!{"email": email.trim().to_lowercase()}).await {
+            Ok(Some(a)) => Ok(a),
+            Ok(None) => Err(AppError::Auth),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    pub async fn update_pass_hash(&self, db: &Database, pass_hash: String) -> Result<(), AppError> {
+        db.collection::<Admin>("admins")
+            .update_one(doc!{"_id": self._id}, doc!{"$set": {"pass_hash": pass_hash}})
+            .await?;
+        Ok(())
+    }
+
+    pub async fn create_indexes(db: &Database) -> Result<(), AppError> {
+        db.collection::<Admin>("admins").create_index(
+            IndexModel::builder()
+                .keys(doc!{"email": 1})
+                .options(IndexOptions::builder().unique(true).build())
+                .build()
+        ).await?;
+
+        Ok(())
+    }
+
+    pub fn response(self) -> ResponseAdmin {
+        ResponseAdmin {
+            id: self._id.to_string(),
+            email: self.email,
+            name: self.name
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use serde::{Serialize, Deserialize};
+use mongodb::{
+    bson::{oid::ObjectId, DateTime, doc},
+    options::IndexOptions,
+    Database,
+    IndexModel
+};
+use futures::stream::TryStreamExt;
+use std::fmt;
+use crate::{
+    app_error::AppError,
+    helpers::{generate_token, hash_token}
+};
+
+const KEY_PREFIX: &str = "isk_";
+const MAX_KEYS_PER_VENDOR: u64 = 10;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum ApiScope {
+    #[serde(rename = "products:read")]
+    ProductsRead,
+    #[serde(rename = "products:write")]
+    ProductsWrite
+}
+
+impl fmt::Display for ApiScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApiScope::ProductsRead => write!(f, "products:read"),
+            ApiScope::ProductsWrite => write!(f, "products:write")
+        }
+    }
+}
+
+/// Long lived key a vendor can use from scripts instead of the session
+/// cookie. Only the hash of the key is stored.
+#[derive(Serialize, Deserialize)]
+pub struct ApiKey {
+    pub _id: ObjectId,
+    pub vendor: ObjectId,
+    pub name: String,
+    pub hint: String,
+    pub key_hash: String,
+    pub scopes: Vec<ApiScope>,
+    pub created_at: DateTime,
+    pub last_used: Option<DateTime>
+}
+
+#[derive(Serialize)]
+pub struct ResponseApiKey {
+    id: String,
+    name: String,
+    hint: String,
+    scopes: Vec<ApiScope>,
+    created_at: String,
+    last_used: Option<String>
+}
+
+impl ApiKey {
+    /// Creates a key for the vendor and returns it along with the raw key,
+    /// which is never shown again.
+    pub async fn create(
+        db: &Database,
+        vendor: ObjectId,
+        name: String,
+        scopes: Vec<ApiScope>
+    ) -> Result<(ApiKey, String), AppError> {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Err(AppError::invalid_input("API key name is required"));
+        }
+        let scopes = normalize_scopes(scopes)?;
+
+        let collection = db.collection::<ApiKey>("api_keys");
+        if collection.count_documents(doc!{"vendor": vendor}).await? >= MAX_KEYS_PER_VENDOR {
+            return Err(AppError::invalid_input("Maximum number of API keys reached"));
+        }
+
+        let raw = format!("{}{}", KEY_PREFIX, generate_token());
+        let key = ApiKey {
+            _id: ObjectId::new(),
+            vendor,
+            name,
+            hint: raw[raw.len() - 4..].to_string(),
+            key_hash: hash_token(&raw),
+            scopes,
+            created_at: DateTime::now(),
+            last_used: None
+        };
+
+        collection.insert_one(&key).await?;
+        Ok((key, raw))
+    }
+
+    pub async fn find_by_key(db: &Database, raw: &str) -> Result<ApiKey, AppError> {
+        if !raw.starts_with(KEY_PREFIX) {
+            return Err(AppError::Auth);
+        }
+
+        match db.collection::<ApiKey>("api_keys").find_one(doc!{"key_hash": hash_token(raw)}).await {
+            Ok(Some(k)) => Ok(k),
+            Ok(None) => Err(AppError::Auth),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    pub async fn find_by_vendor(db: &Database, vendor: ObjectId) -> Result<Vec<ApiKey>, AppError> {
+        let cursor = db.collection::<ApiKey>("api_keys")
+            .find(doc!{"vendor": vendor})
+            .sort(doc!{"created_at": -1})
+            .await?;
+
+        let keys: Vec<ApiKey> = cursor.try_collect().await?;
+        Ok(keys)
+    }
+
+    pub fn ensure_scope(&self, scope: ApiScope) -> Result<(), AppError> {
+        match self.scopes.contains(&scope) {
+            true => Ok(()),
+            false => Err(AppError::forbidden(&format!("API key is missing the {} scope", scope)))
+        }
+    }
+
+    pub async fn mark_used(&self, db: &Database) -> Result<(), AppError> {
+        db.collection::<ApiKey>("api_keys")
+            .update_one(doc!{"_id": self._id}, doc!{"$set": {"last_used": DateTime::now()}})
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_by_id(db: &Database, id: ObjectId, vendor: ObjectId) -> Result<ApiKey, AppError> {
+        match db.collection::<ApiKey>("api_keys").find_one_and_delete(doc!{"_id": id, "vendor": vendor}).await {
+            Ok(Some(k)) => Ok(k),
+            Ok(_) => Err(AppError::not_found("API key with this ID does not exist")),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    pub async fn create_indexes(db: &Database) -> Result<(), AppError> {
+        let collection = db.collection::<ApiKey>("api_keys");
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"key_hash": 1})
+                .options(IndexOptions::builder().unique(true).build())
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"vendor": 1})
+                .build()
+        ).await?;
+
+        Ok(())
+    }
+
+    pub fn response(self) -> ResponseApiKey {
+        ResponseApiKey {
+            id: self._id.to_string(),
+            name: self.name,
+            hint: self.hint,
+            scopes: self.scopes,
+            created_at: self.created_at.to_string(),
+            last_used: self.last_used.map(|d| d.to_string())
+        }
+    }
+}
+
+fn normalize_scopes(requested: Vec<ApiScope>) -> Result<Vec<ApiScope>, AppError> {
+    let mut scopes: Vec<ApiScope> = Vec::new();
+    for scope in requested {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    if scopes.is_empty() {
+        return Err(AppError::invalid_input("API key must have at least one scope"));
+    }
+
+    //Writing products is no use without being able to read them
+    if scopes.contains(&ApiScope::ProductsWrite) && !scopes.contains(&ApiScope::ProductsRead) {
+        scopes.insert(0, ApiScope::ProductsRead);
+    }
+
+    Ok(scopes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //ApiScope
+    #[test]
+    fn scope_display_matches_serde() {
+        for scope in [ApiScope::ProductsRead, ApiScope::ProductsWrite] {
+            let serialized = serde_json::to_value(scope).unwrap();
+            assert_eq!(serialized.as_str().unwrap(), scope.to_string());
+        }
+    }
+
+    //normalize_scopes
+    #[test]
+    fn rejects_empty_scopes() {
+        let result = normalize_scopes(Vec::new());
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn write_implies_read() {
+        let result = normalize_scopes(vec![ApiScope::ProductsWrite]).unwrap();
+        assert_eq!(result, vec![ApiScope::ProductsRead, ApiScope::ProductsWrite]);
+    }
+
+    //ensure_scope
+    #[test]
+    fn rejects_missing_scope() {
+        let key = ApiKey {
+            _id: ObjectId::new(),
+            vendor: ObjectId::new(),
+            name: String::from("POS"),
+            hint: String::from("abcd"),
+            key_hash: String::from("hash"),
+            scopes: vec![ApiScope::ProductsRead],
+            created_at: DateTime::now(),
+            last_used: None
+        };
+
+        assert!(key.ensure_scope(ApiScope::ProductsRead).is_ok());
+        assert!(matches!(key.ensure_scope(ApiScope::ProductsWrite), Err(AppError::Forbidden(_))));
+    }
+}
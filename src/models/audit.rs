@@ -0,0 +1,331 @@
+use serde::{Serialize, Deserialize};
+use mongodb::{
+    bson::{self, oid::ObjectId, Bson, DateTime, Document, doc},
+    Database,
+    IndexModel
+};
+use futures::stream::TryStreamExt;
+use std::collections::BTreeSet;
+use crate::app_error::AppError;
+
+const REDACTED_FIELDS: [&str; 3] = ["pass_hash", "two_factor", "key_hash"];
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ActorKind {
+    Vendor,
+    Staff,
+    ApiKey,
+    Admin,
+    Operator,
+    Stripe
+}
+
+/// Who made a change. `id` is the vendor, staff member, API key or admin,
+/// and is empty for the operator token and Stripe webhooks.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Actor {
+    pub kind: ActorKind,
+    pub id: Option<ObjectId>
+}
+
+impl Actor {
+    pub fn new(kind: ActorKind, id: ObjectId) -> Actor {
+        Actor {kind, id: Some(id)}
+    }
+
+    pub fn operator() -> Actor {
+        Actor {kind: ActorKind::Operator, id: None}
+    }
+
+    pub fn stripe() -> Actor {
+        Actor {kind: ActorKind::Stripe, id: None}
+    }
+}
+
+/// Append only record of a change. Entries are never updated or deleted.
+#[derive(Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub _id: ObjectId,
+    pub vendor: Option<ObjectId>,
+    pub actor: Actor,
+    pub action: String,
+    pub target: Option<ObjectId>,
+    pub ip: Option<String>,
+    pub changes: Document,
+    pub created_at: DateTime
+}
+
+#[derive(Serialize)]
+pub struct ResponseAuditEntry {
+    id: String,
+    vendor: Option<String>,
+    actor: ResponseActor,
+    action: String,
+    target: Option<String>,
+    ip: Option<String>,
+    changes: serde_json::Value,
+    created_at: String
+}
+
+#[derive(Serialize)]
+pub struct ResponseActor {
+    kind: ActorKind,
+    id: Option<String>
+}
+
+impl AuditEntry {
+    /// Saves an entry. The change being recorded has already happened, so a
+    /// failure here is logged instead of failing the request.
+    pub async fn record(
+        db: &Database,
+        actor: Actor,
+        vendor: Option<ObjectId>,
+        action: &str,
+        target: Option<ObjectId>,
+        ip: Option<String>,
+        changes: Document
+    ) {
+        let entry = AuditEntry {
+            _id: ObjectId::new(),
+            vendor,
+            actor,
+            action: action.to_string(),
+            target,
+            ip,
+            changes,
+            created_at: DateTime::now()
+        };
+
+        if let Err(e) = db.collection::<AuditEntry>("audit_log").insert_one(&entry).await {
+            eprintln!("Failed to write audit entry {}: {:?}", action, e);
+        }
+    }
+
+    pub async fn find(
+        db: &Database,
+        filter: Document,
+        page: u64,
+        results: u64
+    ) -> Result<Vec<AuditEntry>, AppError> {
+        let cursor = db.collection::<AuditEntry>("audit_log")
+            .find(filter)
+            .sort(doc!{"created_at": -1})
+            .skip(page * results)
+            .limit(results as i64)
+            .await?;
+
+        let entries: Vec<AuditEntry> = cursor.try_collect().await?;
+        Ok(entries)
+    }
+
+    pub async fn create_indexes(db: &Database) -> Result<(), AppError> {
+        let collection = db.collection::<AuditEntry>("audit_log");
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"vendor": 1, "created_at": -1})
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"created_at": -1})
+                .build()
+        ).await?;
+
+        Ok(())
+    }
+
+    pub fn response(self) -> ResponseAuditEntry {
+        ResponseAuditEntry {
+            id: self._id.to_string(),
+            vendor: self.vendor.map(|v| v.to_string()),
+            actor: ResponseActor {
+                kind: self.actor.kind,
+                id: self.actor.id.map(|i| i.to_string())
+            },
+            action: self.action,
+            target: self.target.map(|t| t.to_string()),
+            ip: self.ip,
+            changes: Bson::Document(self.changes).into_relaxed_extjson(),
+            created_at: self.created_at.to_string()
+        }
+    }
+}
+
+/// Serializes a model so it can be passed to `diff`.
+pub fn to_document<T: Serialize>(value: &T) -> Document {
+    bson::to_document(value).unwrap_or_default()
+}
+
+/// Lists the top level fields that differ between two versions of a document
+/// as `{field: {before, after}}`. Secrets are redacted.
+pub fn diff(before: &Document, after: &Document) -> Document {
+    let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    let mut changes = Document::new();
+
+    for key in keys {
+        let old = before.get(key).cloned().unwrap_or(Bson::Null);
+        let new = after.get(key).cloned().unwrap_or(Bson::Null);
+        if old == new {
+            continue;
+        }
+
+        match REDACTED_FIELDS.contains(&key.as_str()) {
+            true => changes.insert(key, doc!{"before": "[redacted]", "after": "[redacted]"}),
+            false => changes.insert(key, doc!{"before": old, "after": new})
+        };
+    }
+
+    changes
+}
+
+/// Diff for a `$set` document, whose keys may be dotted paths, against the
+/// document it is about to be applied to.
+pub fn set_diff(before: &Document, set: &Document) -> Document {
+    let mut changes = Document::new();
+
+    for (key, new) in set {
+        let old = lookup(before, key).cloned().unwrap_or(Bson::Null);
+        if &old == new {
+            continue;
+        }
+
+        let root = key.split('.').next().unwrap_or(key);
+        match REDACTED_FIELDS.contains(&root) {
+            true => changes.insert(key, doc!{"before": "[redacted]", "after": "[redacted]"}),
+            false => changes.insert(key, doc!{"before": old, "after": new.clone()})
+        };
+    }
+
+    changes
+}
+
+/// Diff for an update document against the document it was applied to,
+/// without reading the result back. Handles top level `$set`, `$push` (with
+/// or without `$each`) and `$pullAll`, the operators product updates use.
+pub fn update_diff(before: &Document, update: &Document) -> Document {
+    let mut after = before.clone();
+
+    for (operator, fields) in update {
+        let fields = match fields.as_document() {
+            Some(f) => f,
+            None => continue
+        };
+
+        for (key, value) in fields {
+            match operator.as_str() {
+                "$set" => {
+                    after.insert(key, value.clone());
+                },
+                "$push" => {
+                    let added = match value.as_document().and_then(|v| v.get_array("$each").ok()) {
+                        Some(each) => each.clone(),
+                        None => vec![value.clone()]
+                    };
+                    let mut list = after.get_array(key).cloned().unwrap_or_default();
+                    list.extend(added);
+                    after.insert(key, list);
+                },
+                "$pullAll" => {
+                    let removed = value.as_array().cloned().unwrap_or_default();
+                    if let Ok(list) = after.get_array(key) {
+                        let kept: Vec<Bson> = list.iter().filter(|v| !removed.contains(v)).cloned().collect();
+                        after.insert(key, kept);
+                    }
+                },
+                _ => ()
+            }
+        }
+    }
+
+    diff(before, &after)
+}
+
+fn lookup<'a>(document: &'a Document, path: &str) -> Option<&'a Bson> {
+    let mut parts = path.split('.');
+    let mut value = document.get(parts.next()?)?;
+    for part in parts {
+        value = value.as_document()?.get(part)?;
+    }
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //diff
+    #[test]
+    fn only_lists_changed_fields() {
+        let before = doc!{"name": "Bait", "tags": ["fish"]};
+        let after = doc!{"name": "Live Bait", "tags": ["fish"]};
+
+        let result = diff(&before, &after);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result.get_document("name").unwrap().get_str("after").unwrap(), "Live Bait");
+    }
+
+    #[test]
+    fn records_added_and_removed_fields() {
+        let result = diff(&Document::new(), &doc!{"name": "Bait"});
+        assert_eq!(result.get_document("name").unwrap().get("before"), Some(&Bson::Null));
+
+        let result = diff(&doc!{"name": "Bait"}, &Document::new());
+        assert_eq!(result.get_document("name").unwrap().get("after"), Some(&Bson::Null));
+    }
+
+    //set_diff
+    #[test]
+    fn follows_dotted_paths() {
+        let before = doc!{"public_data": {"slogan": "Old", "phone": "555"}};
+        let set = doc!{"public_data.slogan": "New", "public_data.phone": "555"};
+
+        let result = set_diff(&before, &set);
+        assert_eq!(result.len(), 1);
+        let slogan = result.get_document("public_data.slogan").unwrap();
+        assert_eq!(slogan.get_str("before").unwrap(), "Old");
+        assert_eq!(slogan.get_str("after").unwrap(), "New");
+    }
+
+    //update_diff
+    #[test]
+    fn applies_set_and_push() {
+        let before = doc!{"name": "Bait", "images": ["/a.jpg"], "thumbnail": null};
+        let update = doc!{"$push": {"images": {"$each": ["/b.jpg"]}}, "$set": {"thumbnail": "/b.jpg"}};
+
+        let result = update_diff(&before, &update);
+        assert_eq!(result.len(), 2);
+        let images = result.get_document("images").unwrap();
+        assert_eq!(images.get_array("after").unwrap(), &vec![Bson::from("/a.jpg"), Bson::from("/b.jpg")]);
+        assert_eq!(result.get_document("thumbnail").unwrap().get_str("after").unwrap(), "/b.jpg");
+    }
+
+    #[test]
+    fn applies_pull_all() {
+        let before = doc!{"images": ["/a.jpg", "/b.jpg"]};
+        let result = update_diff(&before, &doc!{"$pullAll": {"images": ["/a.jpg", "/c.jpg"]}});
+
+        let images = result.get_document("images").unwrap();
+        assert_eq!(images.get_array("after").unwrap(), &vec![Bson::from("/b.jpg")]);
+    }
+
+    #[test]
+    fn unchanged_update_has_no_diff() {
+        let before = doc!{"name": "Bait"};
+        assert!(update_diff(&before, &doc!{"$set": {"name": "Bait"}}).is_empty());
+    }
+
+    #[test]
+    fn redacts_nested_secrets() {
+        let result = set_diff(&doc!{}, &doc!{"two_factor.secret": "abc"});
+        assert_eq!(result.get_document("two_factor.secret").unwrap().get_str("after").unwrap(), "[redacted]");
+    }
+
+    #[test]
+    fn redacts_secrets() {
+        let result = diff(&doc!{"pass_hash": "old"}, &doc!{"pass_hash": "new"});
+        assert_eq!(result.get_document("pass_hash").unwrap().get_str("after").unwrap(), "[redacted]");
+    }
+}
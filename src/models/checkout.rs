@@ -0,0 +1,110 @@
+use serde::{Serialize, Deserialize};
+use mongodb::{
+    bson::{oid::ObjectId, DateTime, doc},
+    options::IndexOptions,
+    Database,
+    IndexModel
+};
+use std::time::Duration;
+use crate::app_error::AppError;
+
+const EXPIRES_AFTER: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// A product price as it was when the shopper checked out.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LineItem {
+    pub product: ObjectId,
+    pub price: ObjectId,
+    pub name: String,
+    pub descriptor: String,
+    pub unit_price: i32,
+    pub shipping: i32,
+    pub quantity: i32
+}
+
+#[derive(Serialize)]
+pub struct ResponseLineItem {
+    product: String,
+    price: String,
+    name: String,
+    descriptor: String,
+    unit_price: i32,
+    shipping: i32,
+    quantity: i32
+}
+
+/// Snapshot of a cart sent to Stripe Checkout, kept so the order can be
+/// created from what the shopper saw once payment completes.
+#[derive(Serialize, Deserialize)]
+pub struct Checkout {
+    pub _id: ObjectId,
+    pub session_id: String,
+    pub vendor: ObjectId,
+    pub customer: Option<ObjectId>,
+    pub items: Vec<LineItem>,
+    #[serde(default)]
+    pub reservations: Vec<ObjectId>,
+    pub subtotal: i64,
+    pub shipping: i64,
+    pub application_fee: i64,
+    pub created_at: DateTime
+}
+
+impl LineItem {
+    pub fn subtotal(&self) -> i64 {
+        self.unit_price as i64 * self.quantity as i64
+    }
+
+    pub fn shipping_total(&self) -> i64 {
+        self.shipping as i64 * self.quantity as i64
+    }
+
+    pub fn response(self) -> ResponseLineItem {
+        ResponseLineItem {
+            product: self.product.to_string(),
+            price: self.price.to_string(),
+            name: self.name,
+            descriptor: self.descriptor,
+            unit_price: self.unit_price,
+            shipping: self.shipping,
+            quantity: self.quantity
+        }
+    }
+}
+
+impl Checkout {
+    pub async fn insert(&self, db: &Database) -> Result<(), AppError> {
+        match db.collection::<Checkout>("checkouts").insert_one(self).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    pub async fn find_by_session(db: &Database, session_id: &str) -> Result<Checkout, AppError> {
+        match db.collection::<Checkout>("checkouts").find_one(doc!{"session_id": session_id}).await {
+            Ok(Some(c)) => Ok(c),
+            Ok(None) => Err(AppError::not_found("Checkout does not exist")),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    pub async fn create_indexes(db: &Database) -> Result<(), AppError> {
+        let collection = db.collection::<Checkout>("checkouts");
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"session_id": 1})
+                .options(IndexOptions::builder().unique(true).build())
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"created_at": 1})
+                .options(IndexOptions::builder().expire_after(EXPIRES_AFTER).build())
+                .build()
+        ).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use serde::{Serialize, Deserialize};
+use mongodb::{
+    bson::{oid::ObjectId, DateTime, Document, doc},
+    options::{IndexOptions, ReturnDocument},
+    Database,
+    IndexModel
+};
+use crate::{
+    app_error::AppError,
+    helpers::{email_collation, drop_uncollated_email_index}
+};
+
+/// Shopper account. Separate from vendors, with its own cookie and sessions.
+#[derive(Serialize, Deserialize)]
+pub struct Customer {
+    pub _id: ObjectId,
+    pub email: String,
+    pub name: String,
+    pub phone: Option<String>,
+    pub shipping_address: Option<ShippingAddress>,
+    pub pass_hash: String,
+    pub created_at: DateTime
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ShippingAddress {
+    pub name: String,
+    pub line1: String,
+    pub line2: Option<String>,
+    pub city: String,
+    pub state: String,
+    pub postal_code: String,
+    pub country: String
+}
+
+#[derive(Serialize)]
+pub struct ResponseCustomer {
+    id: String,
+    email: String,
+    name: String,
+    phone: Option<String>,
+    shipping_address: Option<ShippingAddress>
+}
+
+impl Customer {
+    pub fn new(email: String, name: String, pass_hash: String) -> Customer {
+        Customer {
+            _id: ObjectId::new(),
+            email,
+            name,
+            phone: None,
+            shipping_address: None,
+            pass_hash,
+            created_at: DateTime::now()
+        }
+    }
+
+    pub async fn insert(&self, db: &Database) -> Result<(), AppError> {
+        match db.collection::<Customer>("customers").insert_one(self).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(AppError::from_write(e, "An account with this email already exists"))
+        }
+    }
+
+    pub async fn find_by_id(db: &Database, customer_id: ObjectId) -> Result<Customer, AppError> {
+        match db.collection::<Customer>("customers").find_one(doc!{"_id": customer_id}).await {
+            Ok(Some(c)) => Ok(c),
+            Ok(None) => Err(AppError::not_found("Customer with this ID does not exist")),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    /// Case insensitive, like the vendor lookup.
+    pub async fn find_by_email(db: &Database, email: &str) -> Result<Customer, AppError> {
+        let result = db.collection::<Customer>("customers")
+            .find_one(doc!{"email": email.trim().to_lowercase()})
+            .collation(email_collation())
+            .await;
+
+        match result {
+            Ok(Some(c)) => Ok(c),
+            Ok(None) => Err(AppError::not_found("Customer with this email does not exist")),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    /// Applies the `$set` document and returns the updated customer.
+    pub async fn update(&self, db: &Database, data: Document) -> Result<Customer, AppError> {
+        let result = db.collection::<Customer>("customers")
+            .find_one_and_update(doc!{"_id": self._id}, doc!{"$set": data})
+            .return_document(ReturnDocument::After)
+            .await;
+
+        match result {
+            Ok(Some(c)) => Ok(c),
+            Ok(None) => Err(AppError::not_found("Customer with this ID does not exist")),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    /// The email index was first built without a collation.
+    pub async fn migrate(db: &Database) -> Result<(), AppError> {
+        drop_uncollated_email_index(&db.collection::<Customer>("customers")).await
+    }
+
+    pub async fn create_indexes(db: &Database) -> Result<(), AppError> {
+        db.collection::<Customer>("customers").create_index(
+            IndexModel::builder()
+                .keys(doc!{"email": 1})
+                .options(IndexOptions::builder().unique(true).collation(email_collation()).build())
+                .build()
+        ).await?;
+
+        Ok(())
+    }
+
+    pub fn response(self) -> ResponseCustomer {
+        ResponseCustomer {
+            id: self._id.to_string(),
+            email: self.email,
+            name: self.name,
+            phone: self.phone,
+            shipping_address: self.shipping_address
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Serialize, Deserialize};
+use mongodb::{
+    bson::{oid::ObjectId, DateTime, doc},
+    options::IndexOptions,
+    Database,
+    IndexModel
+};
+use std::time::Duration;
+use crate::{
+    app_error::AppError,
+    helpers::{generate_token, hash_token}
+};
+
+const CHALLENGE_LENGTH_MS: i64 = 5 * 60 * 1000;
+
+/// A password (or other first factor) that has been verified for a vendor
+/// with two-factor authentication, waiting on the second factor.
+#[derive(Serialize, Deserialize)]
+pub struct LoginChallenge {
+    pub _id: ObjectId,
+    pub vendor: ObjectId,
+    pub token_hash: String,
+    pub expires_at: DateTime
+}
+
+impl LoginChallenge {
+    pub async fn create(db: &Database, vendor: ObjectId) -> Result<(LoginChallenge, String), AppError> {
+        let token = generate_token();
+        let challenge = LoginChallenge {
+            _id: ObjectId::new(),
+            vendor,
+            token_hash: hash_token(&token),
+            expires_at: DateTime::from_millis(DateTime::now().timestamp_millis() + CHALLENGE_LENGTH_MS)
+        };
+
+        db.collection::<LoginChallenge>("login_challenges").insert_one(&challenge).await?;
+        Ok((challenge, token))
+    }
+
+    pub async fn find_by_token(db: &Database, token: &str) -> Result<LoginChallenge, AppError> {
+        let filter = doc!{
+            "token_hash": hash_token(token),
+            "expires_at": {"$gt": DateTime::now()}
+        };
+
+        match db.collection::<LoginChallenge>("login_challenges").find_one(filter).await {
+            Ok(Some(c)) => Ok(c),
+            Ok(None) => Err(AppError::Auth),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    pub async fn delete(&self, db: &Database) -> Result<(), AppError> {
+        db.collection::<LoginChallenge>("login_challenges")
+            .delete_one(doc!{"_id": self._id})
+            .await?;
+        Ok(())
+    }
+
+    pub async fn create_indexes(db: &Database) -> Result<(), AppError> {
+        let collection = db.collection::<LoginChallenge>("login_challenges");
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"token_hash": 1})
+                .options(IndexOptions::builder().unique(true).build())
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"expires_at": 1})
+                .options(IndexOptions::builder().expire_after(Duration::from_secs(0)).build())
+                .build()
+        ).await?;
+
+        Ok(())
+    }
+}
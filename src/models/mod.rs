@@ -1,2 +1,16 @@
 pub mod vendor;
 pub mod product;
+pub mod session;
+pub mod throttle;
+pub mod login_challenge;
+pub mod token;
+pub mod admin;
+pub mod api_key;
+pub mod staff;
+pub mod customer;
+pub mod audit;
+pub mod checkout;
+pub mod order;
+pub mod stripe_event;
+pub mod reservation;
+pub mod refund;
@@ -0,0 +1,456 @@
+use serde::{Serialize, Deserialize};
+use mongodb::{
+    bson::{self, oid::ObjectId, DateTime, Document, doc},
+    options::IndexOptions,
+    Database,
+    IndexModel
+};
+use futures::stream::TryStreamExt;
+use crate::{
+    app_error::AppError,
+    models::{
+        checkout::{Checkout, LineItem, ResponseLineItem},
+        customer::ShippingAddress,
+        vendor::Vendor
+    },
+    emails::{send_email_with_retry, new_order, order_receipt}
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderStatus {
+    Paid,
+    Fulfilled,
+    Shipped,
+    Completed,
+    Cancelled
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Contact {
+    pub name: String,
+    pub email: String,
+    pub phone: Option<String>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Payment {
+    pub session_id: String,
+    pub payment_intent: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Dispute {
+    pub id: String,
+    pub status: String,
+    pub reason: String,
+    pub amount: i64
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Order {
+    pub _id: ObjectId,
+    pub vendor: ObjectId,
+    pub customer: Option<ObjectId>,
+    pub items: Vec<LineItem>,
+    pub contact: Contact,
+    pub shipping_address: Option<ShippingAddress>,
+    pub subtotal: i64,
+    pub shipping: i64,
+    pub total: i64,
+    pub status: OrderStatus,
+    pub tracking: Option<String>,
+    pub payment: Payment,
+    #[serde(default)]
+    pub refunded: i64,
+    #[serde(default)]
+    pub dispute: Option<Dispute>,
+    pub created_at: DateTime,
+    pub updated_at: DateTime
+}
+
+#[derive(Serialize)]
+pub struct ResponseOrder {
+    id: String,
+    customer: Option<String>,
+    items: Vec<ResponseLineItem>,
+    contact: Contact,
+    shipping_address: Option<ShippingAddress>,
+    subtotal: i64,
+    shipping: i64,
+    total: i64,
+    status: OrderStatus,
+    tracking: Option<String>,
+    payment_intent: Option<String>,
+    refunded: i64,
+    dispute: Option<Dispute>,
+    created_at: String,
+    updated_at: String
+}
+
+impl OrderStatus {
+    /// Orders move forward from paid to fulfilled (picked up) or shipped and
+    /// then completed. Only paid orders can be cancelled.
+    pub fn can_become(self, next: OrderStatus) -> bool {
+        matches!(
+            (self, next),
+            (OrderStatus::Paid, OrderStatus::Fulfilled)
+                | (OrderStatus::Paid, OrderStatus::Shipped)
+                | (OrderStatus::Paid, OrderStatus::Cancelled)
+                | (OrderStatus::Fulfilled, OrderStatus::Completed)
+                | (OrderStatus::Shipped, OrderStatus::Completed)
+        )
+    }
+}
+
+impl Order {
+    pub fn from_checkout(
+        checkout: Checkout,
+        contact: Contact,
+        shipping_address: Option<ShippingAddress>,
+        payment_intent: Option<String>
+    ) -> Order {
+        let now = DateTime::now();
+        Order {
+            _id: ObjectId::new(),
+            vendor: checkout.vendor,
+            customer: checkout.customer,
+            items: checkout.items,
+            contact,
+            shipping_address,
+            subtotal: checkout.subtotal,
+            shipping: checkout.shipping,
+            total: checkout.subtotal + checkout.shipping,
+            status: OrderStatus::Paid,
+            tracking: None,
+            payment: Payment {
+                session_id: checkout.session_id,
+                payment_intent
+            },
+            refunded: 0,
+            dispute: None,
+            created_at: now,
+            updated_at: now
+        }
+    }
+
+    /// Returns false if an order already exists for the checkout session.
+    pub async fn insert(&self, db: &Database) -> Result<bool, AppError> {
+        match db.collection::<Order>("orders").insert_one(self).await {
+            Ok(_) => Ok(true),
+            Err(e) => match AppError::from_write(e, "Order already exists") {
+                AppError::Conflict(_) => Ok(false),
+                e => Err(e)
+            }
+        }
+    }
+
+    /// Emails the vendor if they have new order emails turned on, then the
+    /// shopper a receipt. A failed vendor email is logged so the receipt is
+    /// still sent.
+    pub async fn send_emails(&self, db: &Database) -> Result<(), AppError> {
+        let vendor = Vendor::find_by_id(db, self.vendor).await?;
+
+        if vendor.new_order_send_email {
+            let result = send_email_with_retry(
+                &vendor.email,
+                &vendor.owner,
+                "You have a new order",
+                new_order(&vendor.owner, self)
+            ).await;
+            if let Err(e) = result {
+                eprintln!("Failed to send new order email for order {}: {:?}", self._id, e);
+            }
+        }
+
+        if self.contact.email.is_empty() {
+            return Ok(());
+        }
+        send_email_with_retry(
+            &self.contact.email,
+            &self.contact.name,
+            &format!("Your order from {}", vendor.store),
+            order_receipt(&vendor.store, self)
+        ).await
+    }
+
+    pub async fn find_for_vendor(db: &Database, id: ObjectId, vendor: ObjectId) -> Result<Order, AppError> {
+        match db.collection::<Order>("orders").find_one(doc!{"_id": id, "vendor": vendor}).await {
+            Ok(Some(o)) => Ok(o),
+            Ok(None) => Err(AppError::not_found("Order with this ID does not exist")),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    pub async fn find_by_payment_intent(db: &Database, payment_intent: &str) -> Result<Option<Order>, AppError> {
+        match db.collection::<Order>("orders").find_one(doc!{"payment.payment_intent": payment_intent}).await {
+            Ok(o) => Ok(o),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    pub async fn find_by_vendor(
+        db: &Database,
+        vendor: ObjectId,
+        status: Option<OrderStatus>,
+        page: u64,
+        results: u64
+    ) -> Result<Vec<Order>, AppError> {
+        let mut filter = doc!{"vendor": vendor};
+        if let Some(s) = status {
+            filter.insert("status", bson::to_bson(&s).map_err(|_| AppError::InternalError)?);
+        }
+
+        let cursor = db.collection::<Order>("orders")
+            .find(filter)
+            .sort(doc!{"created_at": -1})
+            .skip(page * results)
+            .limit(results as i64)
+            .await?;
+
+        let orders: Vec<Order> = cursor.try_collect().await?;
+        Ok(orders)
+    }
+
+    /// Moves the order to `next`, failing if the transition isn't allowed or
+    /// if the order changed since it was read. Returns the changed fields.
+    pub async fn set_status(
+        &mut self,
+        db: &Database,
+        next: OrderStatus,
+        tracking: Option<String>
+    ) -> Result<Document, AppError> {
+        let current_bson = bson::to_bson(&self.status).map_err(|_| AppError::InternalError)?;
+        let next_bson = bson::to_bson(&next).map_err(|_| AppError::InternalError)?;
+        if !self.status.can_become(next) {
+            return Err(AppError::invalid_input(&format!(
+                "Order cannot go from {} to {}",
+                current_bson.as_str().unwrap_or_default(),
+                next_bson.as_str().unwrap_or_default()
+            )));
+        }
+
+        let now = DateTime::now();
+        let mut set = doc!{"status": &next_bson, "updated_at": now};
+        if let Some(t) = &tracking {
+            set.insert("tracking", t);
+        }
+
+        let result = db.collection::<Order>("orders")
+            .update_one(
+                doc!{"_id": self._id, "status": &current_bson},
+                doc!{"$set": &set}
+            )
+            .await?;
+        if result.matched_count == 0 {
+            return Err(AppError::conflict("Order was changed by someone else, reload and try again"));
+        }
+
+        let changes = doc!{"status": {"before": current_bson, "after": next_bson}};
+        self.status = next;
+        if tracking.is_some() {
+            self.tracking = tracking;
+        }
+        self.updated_at = now;
+        Ok(changes)
+    }
+
+    /// Records the total refunded on the order's payment. Refunds only add
+    /// up, so a lower total from an event that arrived late is ignored.
+    /// Returns the order as it was before, or None if no order has the
+    /// payment intent.
+    pub async fn set_refunded(
+        db: &Database,
+        payment_intent: &str,
+        refunded: i64
+    ) -> Result<Option<Order>, AppError> {
+        Order::update_by_payment(db, payment_intent, doc!{
+            "$max": {"refunded": refunded},
+            "$set": {"updated_at": DateTime::now()}
+        }).await
+    }
+
+    pub async fn set_dispute(
+        db: &Database,
+        payment_intent: &str,
+        dispute: &Dispute
+    ) -> Result<Option<Order>, AppError> {
+        let dispute = bson::to_bson(dispute).map_err(|_| AppError::InternalError)?;
+        Order::update_by_payment(db, payment_intent, doc!{
+            "$set": {"dispute": dispute, "updated_at": DateTime::now()}
+        }).await
+    }
+
+    async fn update_by_payment(
+        db: &Database,
+        payment_intent: &str,
+        update: Document
+    ) -> Result<Option<Order>, AppError> {
+        match db.collection::<Order>("orders")
+            .find_one_and_update(doc!{"payment.payment_intent": payment_intent}, update)
+            .await {
+                Ok(o) => Ok(o),
+                Err(e) => Err(AppError::Database(e))
+            }
+    }
+
+    pub async fn create_indexes(db: &Database) -> Result<(), AppError> {
+        let collection = db.collection::<Order>("orders");
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"vendor": 1, "created_at": -1})
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"payment.session_id": 1})
+                .options(IndexOptions::builder().unique(true).build())
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"payment.payment_intent": 1})
+                .build()
+        ).await?;
+
+        Ok(())
+    }
+
+    pub fn response(self) -> ResponseOrder {
+        ResponseOrder {
+            id: self._id.to_string(),
+            customer: self.customer.map(|c| c.to_string()),
+            items: self.items.into_iter().map(LineItem::response).collect(),
+            contact: self.contact,
+            shipping_address: self.shipping_address,
+            subtotal: self.subtotal,
+            shipping: self.shipping,
+            total: self.total,
+            status: self.status,
+            tracking: self.tracking,
+            payment_intent: self.payment.payment_intent,
+            refunded: self.refunded,
+            dispute: self.dispute,
+            created_at: self.created_at.to_string(),
+            updated_at: self.updated_at.to_string()
+        }
+    }
+}
+
+/// Builds orders for tests elsewhere. Starts as a paid $10.00 order for two
+/// units of one item.
+#[cfg(test)]
+pub struct OrderBuilder {
+    order: Order
+}
+
+#[cfg(test)]
+impl Order {
+    pub fn builder() -> OrderBuilder {
+        let now = DateTime::now();
+        OrderBuilder {
+            order: Order {
+                _id: ObjectId::new(),
+                vendor: ObjectId::new(),
+                customer: None,
+                items: vec![OrderBuilder::line_item(2)],
+                contact: Contact {
+                    name: String::from("Sam"),
+                    email: String::from("sam@inletsites.dev"),
+                    phone: None
+                },
+                shipping_address: None,
+                subtotal: 1000,
+                shipping: 0,
+                total: 1000,
+                status: OrderStatus::Paid,
+                tracking: None,
+                payment: Payment {
+                    session_id: String::from("cs_123"),
+                    payment_intent: Some(String::from("pi_123"))
+                },
+                refunded: 0,
+                dispute: None,
+                created_at: now,
+                updated_at: now
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl OrderBuilder {
+    /// A $5.00 item with its own product and price IDs.
+    pub fn line_item(quantity: i32) -> LineItem {
+        LineItem {
+            product: ObjectId::new(),
+            price: ObjectId::new(),
+            name: String::from("Live Bait"),
+            descriptor: String::from("Dozen"),
+            unit_price: 500,
+            shipping: 0,
+            quantity
+        }
+    }
+
+    /// Replaces the items without touching the totals.
+    pub fn items(mut self, items: Vec<LineItem>) -> OrderBuilder {
+        self.order.items = items;
+        self
+    }
+
+    /// Sets the subtotal and total, with no shipping.
+    pub fn total(mut self, total: i64) -> OrderBuilder {
+        self.order.subtotal = total;
+        self.order.shipping = 0;
+        self.order.total = total;
+        self
+    }
+
+    pub fn refunded(mut self, refunded: i64) -> OrderBuilder {
+        self.order.refunded = refunded;
+        self
+    }
+
+    pub fn contact(mut self, name: &str, email: &str) -> OrderBuilder {
+        self.order.contact.name = name.to_string();
+        self.order.contact.email = email.to_string();
+        self
+    }
+
+    pub fn build(self) -> Order {
+        self.order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //can_become
+    #[test]
+    fn allows_forward_transitions() {
+        assert!(OrderStatus::Paid.can_become(OrderStatus::Fulfilled));
+        assert!(OrderStatus::Paid.can_become(OrderStatus::Shipped));
+        assert!(OrderStatus::Shipped.can_become(OrderStatus::Completed));
+        assert!(OrderStatus::Fulfilled.can_become(OrderStatus::Completed));
+    }
+
+    #[test]
+    fn only_paid_orders_cancel() {
+        assert!(OrderStatus::Paid.can_become(OrderStatus::Cancelled));
+        assert!(!OrderStatus::Shipped.can_become(OrderStatus::Cancelled));
+        assert!(!OrderStatus::Completed.can_become(OrderStatus::Cancelled));
+    }
+
+    #[test]
+    fn rejects_backwards_and_same_status() {
+        assert!(!OrderStatus::Completed.can_become(OrderStatus::Paid));
+        assert!(!OrderStatus::Shipped.can_become(OrderStatus::Paid));
+        assert!(!OrderStatus::Paid.can_become(OrderStatus::Paid));
+        assert!(!OrderStatus::Cancelled.can_become(OrderStatus::Shipped));
+    }
+}
@@ -4,7 +4,7 @@ use mongodb::{
     bson::{DateTime, Document, doc, oid::ObjectId}
 };
 use futures::stream::TryStreamExt;
-use crate::app_error::AppError;
+use crate::{app_error::AppError, models::audit};
 
 #[derive(Serialize, Deserialize)]
 pub struct Product {
@@ -44,7 +44,7 @@ impl Product {
     pub async fn insert(&self, db: &Database) -> Result<(), AppError> {
         match db.collection::<Product>("products").insert_one(self).await {
             Ok(_) => Ok(()),
-            Err(e) => Err(AppError::Database(e.into()))
+            Err(e) => Err(AppError::Database(e))
         }
     }
 
@@ -68,7 +68,25 @@ impl Product {
             .await {
                 Ok(Some(p)) => Ok(p),
                 Ok(None) => Err(AppError::not_found("Product with this ID does not exist")),
-                Err(e) => Err(AppError::Database(e.into()))
+                Err(e) => Err(AppError::Database(e))
+            }
+    }
+
+    pub async fn find_active_by_id<P>(
+        db: &Database,
+        id: ObjectId,
+        proj: Document
+    ) -> Result<P, AppError>
+        where
+            P: DeserializeOwned + Send + Sync + Unpin
+    {
+        match db.collection::<P>("products")
+            .find_one(doc!{"_id": id, "active": true})
+            .projection(proj)
+            .await {
+                Ok(Some(p)) => Ok(p),
+                Ok(None) => Err(AppError::not_found("Product with this ID does not exist")),
+                Err(e) => Err(AppError::Database(e))
             }
     }
 
@@ -84,7 +102,28 @@ impl Product {
     {
         let cursor = db.collection::<P>("products")
             .find(doc!{"vendor": vendor_id})
-            .skip(page * results as u64)
+            .skip(page * results)
+            .limit(results as i64)
+            .projection(proj)
+            .await?;
+
+        let products: Vec<P> = cursor.try_collect().await?;
+        Ok(products)
+    }
+
+    pub async fn find_active_by_vendor<P>(
+        db: &Database,
+        vendor_id: ObjectId,
+        proj: Document,
+        page: u64,
+        results: u64
+    ) -> Result<Vec<P>, AppError>
+    where
+        P: DeserializeOwned + Send + Sync + Unpin
+    {
+        let cursor = db.collection::<P>("products")
+            .find(doc!{"vendor": vendor_id, "active": true})
+            .skip(page * results)
             .limit(results as i64)
             .projection(proj)
             .await?;
@@ -93,32 +132,100 @@ impl Product {
         Ok(products)
     }
 
+    /// Returns the product as it was before the change.
+    pub async fn set_active(db: &Database, id: ObjectId, active: bool) -> Result<Product, AppError> {
+        match db.collection::<Product>("products")
+            .find_one_and_update(doc!{"_id": id}, doc!{"$set": {"active": active}})
+            .await {
+                Ok(Some(p)) => Ok(p),
+                Ok(None) => Err(AppError::not_found("Product with this ID does not exist")),
+                Err(e) => Err(AppError::Database(e))
+            }
+    }
+
+    /// Applies a raw update document and returns the fields it changed. The
+    /// changes are worked out from the document as it was right before the
+    /// update, so a later write can't end up in them.
     pub async fn update(
         db: &Database,
         product_id: ObjectId,
         vendor_id: Option<ObjectId>,
         updates: Document
-    ) -> Result<(), AppError> {
+    ) -> Result<Document, AppError> {
         let filter = match vendor_id {
             Some(v) => doc!{"_id": product_id, "vendor": v},
             None => doc!{"_id": product_id}
         };
+        let collection = db.collection::<Document>("products");
 
-        match db.collection::<Product>("products").update_one(filter, updates).await {
-            Ok(ur) if ur.matched_count == 1 => Ok(()),
-            Ok(_) => Err(AppError::forbidden("You do not have permissions for this product")),
-            Err(e) => Err(AppError::Database(e.into()))
+        match collection.find_one_and_update(filter, updates.clone()).await {
+            Ok(Some(before)) => Ok(audit::update_diff(&before, &updates)),
+            Ok(None) => Err(AppError::forbidden("You do not have permissions for this product")),
+            Err(e) => Err(AppError::Database(e))
         }
     }
 
-    pub async fn delete(db: &Database, id: ObjectId, vendor: ObjectId) -> Result<(), AppError> {
+    pub async fn delete(db: &Database, id: ObjectId, vendor: ObjectId) -> Result<Product, AppError> {
         match db.collection::<Product>("products").find_one_and_delete(doc!{"_id": id, "vendor": vendor}).await {
-            Ok(Some(_)) => Ok(()),
+            Ok(Some(p)) => Ok(p),
             Ok(None) => Err(AppError::forbidden("You do not have authorization for this product")),
-            Err(e) => Err(AppError::Database(e.into()))
+            Err(e) => Err(AppError::Database(e))
         }
     }
 
+    /// Takes units of a price out of stock. Fails with `AppError::OutOfStock`
+    /// rather than letting the quantity go negative.
+    pub async fn decrement_stock(
+        db: &Database,
+        product: ObjectId,
+        price: ObjectId,
+        quantity: i32
+    ) -> Result<(), AppError> {
+        let collection = db.collection::<Product>("products");
+        let result = collection
+            .update_one(
+                doc!{"_id": product, "prices": {"$elemMatch": {"_id": price, "quantity": {"$gte": quantity}}}},
+                doc!{"$inc": {"prices.$.quantity": -quantity}}
+            )
+            .await?;
+        if result.matched_count == 1 {
+            return Ok(());
+        }
+
+        let stock = Product::stock(db, product, price).await?;
+        Err(AppError::OutOfStock(stock.max(0)))
+    }
+
+    pub async fn increment_stock(
+        db: &Database,
+        product: ObjectId,
+        price: ObjectId,
+        quantity: i32
+    ) -> Result<(), AppError> {
+        match db.collection::<Product>("products")
+            .update_one(
+                doc!{"_id": product, "prices._id": price},
+                doc!{"$inc": {"prices.$.quantity": quantity}}
+            )
+            .await {
+                Ok(ur) if ur.matched_count == 1 => Ok(()),
+                Ok(_) => Err(AppError::not_found("Price does not exist")),
+                Err(e) => Err(AppError::Database(e))
+            }
+    }
+
+    async fn stock(db: &Database, product: ObjectId, price: ObjectId) -> Result<i32, AppError> {
+        let found = db.collection::<Product>("products")
+            .find_one(doc!{"_id": product})
+            .await?
+            .ok_or(AppError::not_found("Product with this ID does not exist"))?;
+
+        found.prices.iter()
+            .find(|p| p._id == price)
+            .map(|p| p.quantity)
+            .ok_or(AppError::not_found("Price does not exist"))
+    }
+
     pub async fn verify_ownership(db: &Database, product_id: ObjectId, vendor_id: ObjectId) -> Result<(), AppError> {
         match db.collection::<Document>("products")
             .find_one(doc!{"_id": product_id, "vendor": vendor_id})
@@ -126,7 +233,7 @@ impl Product {
             .await {
                 Ok(Some(_)) => Ok(()),
                 Ok(None) => Err(AppError::forbidden("You do not have authorization for this product")),
-                Err(e) => Err(AppError::Database(e.into()))
+                Err(e) => Err(AppError::Database(e))
             }
     }
 }
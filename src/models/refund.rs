@@ -0,0 +1,217 @@
+use serde::{Serialize, Deserialize};
+use mongodb::{
+    bson::{self, oid::ObjectId, DateTime, doc},
+    options::IndexOptions,
+    Database,
+    IndexModel
+};
+use futures::stream::TryStreamExt;
+use std::collections::HashMap;
+use crate::{
+    app_error::AppError,
+    models::audit::{Actor, ActorKind}
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RefundReason {
+    Duplicate,
+    Fraudulent,
+    RequestedByCustomer
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RestockItem {
+    pub product: ObjectId,
+    pub price: ObjectId,
+    pub quantity: i32
+}
+
+/// Refund a vendor made from the dashboard. `order` is empty for payments
+/// made directly on the vendor's Stripe account rather than through checkout.
+#[derive(Serialize, Deserialize)]
+pub struct Refund {
+    pub _id: ObjectId,
+    pub vendor: ObjectId,
+    pub order: Option<ObjectId>,
+    pub payment_intent: String,
+    pub stripe_id: String,
+    pub amount: i64,
+    pub reason: Option<RefundReason>,
+    pub status: String,
+    pub restocked: Vec<RestockItem>,
+    pub created_by: Actor,
+    pub created_at: DateTime
+}
+
+#[derive(Serialize)]
+pub struct ResponseRefund {
+    id: String,
+    order: Option<String>,
+    payment_intent: String,
+    stripe_id: String,
+    amount: i64,
+    reason: Option<RefundReason>,
+    status: String,
+    restocked: Vec<ResponseRestockItem>,
+    created_by: ResponseCreatedBy,
+    created_at: String
+}
+
+#[derive(Serialize)]
+pub struct ResponseRestockItem {
+    product: String,
+    price: String,
+    quantity: i32
+}
+
+#[derive(Serialize)]
+pub struct ResponseCreatedBy {
+    kind: ActorKind,
+    id: Option<String>
+}
+
+impl RefundReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RefundReason::Duplicate => "duplicate",
+            RefundReason::Fraudulent => "fraudulent",
+            RefundReason::RequestedByCustomer => "requested_by_customer"
+        }
+    }
+}
+
+impl Refund {
+    /// Returns false if the Stripe refund was already recorded, which happens
+    /// when a request is replayed with the same idempotency key.
+    pub async fn insert(&self, db: &Database) -> Result<bool, AppError> {
+        match db.collection::<Refund>("refunds").insert_one(self).await {
+            Ok(_) => Ok(true),
+            Err(e) => match AppError::from_write(e, "Refund already exists") {
+                AppError::Conflict(_) => Ok(false),
+                e => Err(e)
+            }
+        }
+    }
+
+    pub async fn find_by_stripe_id(db: &Database, stripe_id: &str) -> Result<Refund, AppError> {
+        match db.collection::<Refund>("refunds").find_one(doc!{"stripe_id": stripe_id}).await {
+            Ok(Some(r)) => Ok(r),
+            Ok(None) => Err(AppError::not_found("Refund does not exist")),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    pub async fn set_restocked(&mut self, db: &Database, restocked: Vec<RestockItem>) -> Result<(), AppError> {
+        if restocked.is_empty() {
+            return Ok(());
+        }
+
+        let items = bson::to_bson(&restocked).map_err(|_| AppError::InternalError)?;
+        db.collection::<Refund>("refunds")
+            .update_one(doc!{"_id": self._id}, doc!{"$set": {"restocked": items}})
+            .await?;
+        self.restocked = restocked;
+        Ok(())
+    }
+
+    /// Units of each price already put back in stock by refunds on the order.
+    pub async fn restocked_for_order(db: &Database, order: ObjectId) -> Result<HashMap<ObjectId, i32>, AppError> {
+        let mut cursor = db.collection::<Refund>("refunds")
+            .aggregate(vec![
+                doc!{"$match": {"order": order}},
+                doc!{"$unwind": "$restocked"},
+                doc!{"$group": {"_id": "$restocked.price", "quantity": {"$sum": "$restocked.quantity"}}}
+            ])
+            .await?;
+
+        let mut totals = HashMap::new();
+        while let Some(d) = cursor.try_next().await? {
+            let quantity = d.get_i32("quantity").or_else(|_| d.get_i64("quantity").map(|q| q as i32));
+            if let (Ok(price), Ok(quantity)) = (d.get_object_id("_id"), quantity) {
+                totals.insert(price, quantity);
+            }
+        }
+        Ok(totals)
+    }
+
+    pub async fn find_by_vendor(
+        db: &Database,
+        vendor: ObjectId,
+        page: u64,
+        results: u64
+    ) -> Result<Vec<Refund>, AppError> {
+        let cursor = db.collection::<Refund>("refunds")
+            .find(doc!{"vendor": vendor})
+            .sort(doc!{"created_at": -1})
+            .skip(page * results)
+            .limit(results as i64)
+            .await?;
+
+        let refunds: Vec<Refund> = cursor.try_collect().await?;
+        Ok(refunds)
+    }
+
+    pub async fn create_indexes(db: &Database) -> Result<(), AppError> {
+        let collection = db.collection::<Refund>("refunds");
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"vendor": 1, "created_at": -1})
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"stripe_id": 1})
+                .options(IndexOptions::builder().unique(true).build())
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"order": 1})
+                .build()
+        ).await?;
+
+        Ok(())
+    }
+
+    pub fn response(self) -> ResponseRefund {
+        ResponseRefund {
+            id: self._id.to_string(),
+            order: self.order.map(|o| o.to_string()),
+            payment_intent: self.payment_intent,
+            stripe_id: self.stripe_id,
+            amount: self.amount,
+            reason: self.reason,
+            status: self.status,
+            restocked: self.restocked.into_iter()
+                .map(|r| ResponseRestockItem {
+                    product: r.product.to_string(),
+                    price: r.price.to_string(),
+                    quantity: r.quantity
+                })
+                .collect(),
+            created_by: ResponseCreatedBy {
+                kind: self.created_by.kind,
+                id: self.created_by.id.map(|i| i.to_string())
+            },
+            created_at: self.created_at.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //as_str
+    #[test]
+    fn reason_matches_serde() {
+        for r in [RefundReason::Duplicate, RefundReason::Fraudulent, RefundReason::RequestedByCustomer] {
+            let serialized = serde_json::to_value(r).unwrap();
+            assert_eq!(serialized.as_str().unwrap(), r.as_str());
+        }
+    }
+}
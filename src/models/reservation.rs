@@ -0,0 +1,291 @@
+use serde::{Serialize, Deserialize};
+use mongodb::{
+    bson::{oid::ObjectId, DateTime, Document, doc},
+    options::IndexOptions,
+    Database,
+    IndexModel
+};
+use futures::stream::TryStreamExt;
+use std::{collections::HashMap, time::Duration};
+use crate::{
+    app_error::AppError,
+    helpers::{generate_token, hash_token}
+};
+
+/// Longer than a Checkout Session lasts, so a shopper still paying when the
+/// session is about to expire doesn't lose their stock first.
+pub const RESERVATION_DURATION: Duration = Duration::from_secs(35 * 60);
+/// Most units of one price a single reservation can hold.
+pub const MAX_QUANTITY: i32 = 20;
+/// Most units one shopper can have on hold across all their reservations.
+pub const MAX_HELD: i32 = 50;
+
+/// Units of a price held for a shopper. Held units are subtracted from the
+/// available quantity until the reservation is released or expires.
+#[derive(Serialize, Deserialize)]
+pub struct Reservation {
+    pub _id: ObjectId,
+    pub product: ObjectId,
+    pub price: ObjectId,
+    pub quantity: i32,
+    pub token_hash: Option<String>,
+    #[serde(default)]
+    pub holder: Option<String>,
+    pub expires_at: DateTime,
+    pub created_at: DateTime
+}
+
+#[derive(Serialize)]
+pub struct ResponseReservation {
+    token: String,
+    product: String,
+    price: String,
+    quantity: i32,
+    expires_at: String
+}
+
+impl Reservation {
+    pub fn new(product: ObjectId, price: ObjectId, quantity: i32) -> Reservation {
+        let now = DateTime::now();
+        Reservation {
+            _id: ObjectId::new(),
+            product,
+            price,
+            quantity,
+            token_hash: None,
+            holder: None,
+            expires_at: DateTime::from_millis(now.timestamp_millis() + RESERVATION_DURATION.as_millis() as i64),
+            created_at: now
+        }
+    }
+
+    /// Adds a token the shopper can use to release the reservation or check
+    /// out with it. Only the hash is stored.
+    pub fn with_token(mut self) -> (Reservation, String) {
+        let token = generate_token();
+        self.token_hash = Some(hash_token(&token));
+        (self, token)
+    }
+
+    /// Records who made the reservation so their total can be capped.
+    pub fn held_by(mut self, holder: String) -> Reservation {
+        self.holder = Some(holder);
+        self
+    }
+
+    /// Saves the reservation if `stock` covers it along with every other live
+    /// reservation for the price. The count happens after the insert, so two
+    /// shoppers racing for the last units can both lose but never both win.
+    /// `replacing` is left out of the count so a shopper can trade their
+    /// reservation for a new one.
+    pub async fn hold(
+        &self,
+        db: &Database,
+        stock: i32,
+        replacing: Option<ObjectId>
+    ) -> Result<(), AppError> {
+        let collection = db.collection::<Reservation>("reservations");
+        if self.quantity > stock {
+            return Err(AppError::OutOfStock(stock.max(0)));
+        }
+        collection.insert_one(self).await?;
+
+        let filter = held_filter(self.price, DateTime::now(), replacing);
+        let held = sum_quantities(db, filter).await?.values().sum::<i32>();
+
+        let result = check_held(stock, held, self.quantity);
+        if result.is_err() {
+            collection.delete_one(doc!{"_id": self._id}).await?;
+        }
+        result
+    }
+
+    /// Units the holder has on hold across all prices, leaving out the
+    /// reservations in `excluding`.
+    pub async fn held_by_holder(
+        db: &Database,
+        holder: &str,
+        excluding: &[ObjectId]
+    ) -> Result<i32, AppError> {
+        let totals = sum_quantities(db, doc!{
+            "holder": holder,
+            "_id": {"$nin": excluding},
+            "expires_at": {"$gt": DateTime::now()}
+        }).await?;
+        Ok(totals.values().sum())
+    }
+
+    pub async fn find_by_token(db: &Database, token: &str) -> Result<Reservation, AppError> {
+        match db.collection::<Reservation>("reservations")
+            .find_one(doc!{"token_hash": hash_token(token), "expires_at": {"$gt": DateTime::now()}})
+            .await {
+                Ok(Some(r)) => Ok(r),
+                Ok(None) => Err(AppError::not_found("Reservation does not exist or has expired")),
+                Err(e) => Err(AppError::Database(e))
+            }
+    }
+
+    /// Units currently held for each of the prices.
+    pub async fn reserved(db: &Database, prices: &[ObjectId]) -> Result<HashMap<ObjectId, i32>, AppError> {
+        sum_quantities(db, doc!{
+            "price": {"$in": prices},
+            "expires_at": {"$gt": DateTime::now()}
+        }).await
+    }
+
+    pub async fn release(db: &Database, ids: &[ObjectId]) -> Result<(), AppError> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        db.collection::<Reservation>("reservations")
+            .delete_many(doc!{"_id": {"$in": ids}})
+            .await?;
+        Ok(())
+    }
+
+    pub async fn create_indexes(db: &Database) -> Result<(), AppError> {
+        let collection = db.collection::<Reservation>("reservations");
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"price": 1, "expires_at": 1})
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"token_hash": 1})
+                .options(IndexOptions::builder().sparse(true).build())
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"holder": 1, "expires_at": 1})
+                .options(IndexOptions::builder().sparse(true).build())
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"expires_at": 1})
+                .options(IndexOptions::builder().expire_after(Duration::from_secs(0)).build())
+                .build()
+        ).await?;
+
+        Ok(())
+    }
+
+    pub fn response(self, token: String) -> ResponseReservation {
+        ResponseReservation {
+            token,
+            product: self.product.to_string(),
+            price: self.price.to_string(),
+            quantity: self.quantity,
+            expires_at: self.expires_at.to_string()
+        }
+    }
+}
+
+async fn sum_quantities(db: &Database, filter: Document) -> Result<HashMap<ObjectId, i32>, AppError> {
+    let mut cursor = db.collection::<Reservation>("reservations")
+        .aggregate(vec![
+            doc!{"$match": filter},
+            doc!{"$group": {"_id": "$price", "quantity": {"$sum": "$quantity"}}}
+        ])
+        .await?;
+
+    let mut totals = HashMap::new();
+    while let Some(d) = cursor.try_next().await? {
+        let quantity = d.get_i32("quantity").or_else(|_| d.get_i64("quantity").map(|q| q as i32));
+        if let (Ok(price), Ok(quantity)) = (d.get_object_id("_id"), quantity) {
+            totals.insert(price, quantity);
+        }
+    }
+    Ok(totals)
+}
+
+/// Every live reservation for the price, including the one just inserted.
+fn held_filter(price: ObjectId, now: DateTime, replacing: Option<ObjectId>) -> Document {
+    let mut filter = doc!{"price": price, "expires_at": {"$gt": now}};
+    if let Some(r) = replacing {
+        filter.insert("_id", doc!{"$ne": r});
+    }
+    filter
+}
+
+/// `held` includes the new reservation's `quantity`, so the error reports
+/// what was left before it.
+fn check_held(stock: i32, held: i32, quantity: i32) -> Result<(), AppError> {
+    if held > stock {
+        return Err(AppError::OutOfStock((stock - held + quantity).max(0)));
+    }
+    Ok(())
+}
+
+/// Quantity left to sell once reservations are taken out.
+pub fn available(stock: i32, reserved: Option<&i32>) -> i32 {
+    (stock - reserved.copied().unwrap_or(0)).max(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //new
+    #[test]
+    fn expires_after_duration() {
+        let r = Reservation::new(ObjectId::new(), ObjectId::new(), 1);
+        let diff = r.expires_at.timestamp_millis() - r.created_at.timestamp_millis();
+        assert_eq!(diff, RESERVATION_DURATION.as_millis() as i64);
+    }
+
+    //with_token
+    #[test]
+    fn stores_token_hash() {
+        let (r, token) = Reservation::new(ObjectId::new(), ObjectId::new(), 1).with_token();
+        assert_eq!(r.token_hash, Some(hash_token(&token)));
+    }
+
+    //held_filter
+    #[test]
+    fn counts_reservations_made_after() {
+        let filter = held_filter(ObjectId::new(), DateTime::now(), None);
+        assert!(filter.get("_id").is_none());
+    }
+
+    #[test]
+    fn leaves_out_replaced_reservation() {
+        let replacing = ObjectId::new();
+        let filter = held_filter(ObjectId::new(), DateTime::now(), Some(replacing));
+        assert_eq!(filter.get_document("_id").unwrap(), &doc!{"$ne": replacing});
+    }
+
+    //check_held
+    #[test]
+    fn racing_holds_never_both_win() {
+        //Two shoppers want the last unit. Whichever counts first may only see
+        //itself, but the other counts after both inserts and sees two. If
+        //both insert before either counts, both see two and lose.
+        assert!(check_held(1, 1, 1).is_ok());
+        assert!(matches!(check_held(1, 2, 1), Err(AppError::OutOfStock(0))));
+    }
+
+    #[test]
+    fn reports_stock_left_before_hold() {
+        assert!(matches!(check_held(5, 7, 4), Err(AppError::OutOfStock(2))));
+    }
+
+    //available
+    #[test]
+    fn subtracts_reserved() {
+        assert_eq!(available(5, Some(&2)), 3);
+        assert_eq!(available(5, None), 5);
+    }
+
+    #[test]
+    fn never_negative() {
+        assert_eq!(available(2, Some(&5)), 0);
+    }
+}
@@ -0,0 +1,259 @@
+use serde::{Serialize, Deserialize};
+use mongodb::{
+    bson::{self, oid::ObjectId, DateTime, doc},
+    options::IndexOptions,
+    Database,
+    IndexModel
+};
+use futures::stream::TryStreamExt;
+use std::time::Duration;
+use crate::{
+    app_error::AppError,
+    helpers::{generate_token, hash_token}
+};
+
+const SESSION_LENGTH_DAYS: u64 = 30;
+const LAST_SEEN_INTERVAL_MS: i64 = 5 * 60 * 1000;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionKind {
+    Vendor,
+    Admin,
+    Staff,
+    Customer
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub _id: ObjectId,
+    pub kind: SessionKind,
+    pub account: ObjectId,
+    pub token_hash: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime,
+    pub last_seen: DateTime,
+    pub expires_at: DateTime
+}
+
+#[derive(Serialize)]
+pub struct ResponseSession {
+    id: String,
+    user_agent: Option<String>,
+    ip: Option<String>,
+    created_at: String,
+    last_seen: String,
+    current: bool
+}
+
+impl Session {
+    /// Creates a session for the account and returns it along with the raw
+    /// token. Only the hash of the token is stored.
+    pub async fn create(
+        db: &Database,
+        kind: SessionKind,
+        account: ObjectId,
+        user_agent: Option<String>,
+        ip: Option<String>
+    ) -> Result<(Session, String), AppError> {
+        let token = generate_token();
+        let now = DateTime::now();
+        let session = Session {
+            _id: ObjectId::new(),
+            kind,
+            account,
+            token_hash: hash_token(&token),
+            user_agent,
+            ip,
+            created_at: now,
+            last_seen: now,
+            expires_at: expiration_from(now)
+        };
+
+        db.collection::<Session>("sessions").insert_one(&session).await?;
+        Ok((session, token))
+    }
+
+    pub async fn find_by_token(
+        db: &Database,
+        kind: SessionKind,
+        token: &str
+    ) -> Result<Session, AppError> {
+        Session::find_by_token_kinds(db, &[kind], token).await
+    }
+
+    /// Finds a session of any of the kinds, for cookies that can belong to
+    /// more than one kind of account.
+    pub async fn find_by_token_kinds(
+        db: &Database,
+        kinds: &[SessionKind],
+        token: &str
+    ) -> Result<Session, AppError> {
+        let kinds = bson::to_bson(kinds).map_err(|_| AppError::InternalError)?;
+        let filter = doc!{
+            "token_hash": hash_token(token),
+            "kind": {"$in": kinds},
+            "expires_at": {"$gt": DateTime::now()}
+        };
+
+        match db.collection::<Session>("sessions").find_one(filter).await {
+            Ok(Some(s)) => Ok(s),
+            Ok(None) => Err(AppError::Auth),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    pub async fn find_by_account(db: &Database, account: ObjectId) -> Result<Vec<Session>, AppError> {
+        let cursor = db.collection::<Session>("sessions")
+            .find(doc!{"account": account, "expires_at": {"$gt": DateTime::now()}})
+            .sort(doc!{"last_seen": -1})
+            .await?;
+
+        let sessions: Vec<Session> = cursor.try_collect().await?;
+        Ok(sessions)
+    }
+
+    pub async fn touch(&mut self, db: &Database, ip: Option<String>) -> Result<(), AppError> {
+        let now = DateTime::now();
+        if !needs_touch(self.last_seen, now) && self.ip == ip {
+            return Ok(());
+        }
+
+        db.collection::<Session>("sessions")
+            .update_one(doc!{"_id": self._id}, doc!{"$set": {"last_seen": now, "ip": &ip}})
+            .await?;
+        self.last_seen = now;
+        self.ip = ip;
+        Ok(())
+    }
+
+    pub async fn delete_by_token(db: &Database, token: &str) -> Result<(), AppError> {
+        db.collection::<Session>("sessions")
+            .delete_one(doc!{"token_hash": hash_token(token)})
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_by_id(db: &Database, id: ObjectId, account: ObjectId) -> Result<(), AppError> {
+        match db.collection::<Session>("sessions").delete_one(doc!{"_id": id, "account": account}).await {
+            Ok(dr) if dr.deleted_count == 1 => Ok(()),
+            Ok(_) => Err(AppError::not_found("Session with this ID does not exist")),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    /// Removes every session for the account, optionally keeping one (usually
+    /// the session making the request).
+    pub async fn delete_all(
+        db: &Database,
+        account: ObjectId,
+        keep: Option<ObjectId>
+    ) -> Result<u64, AppError> {
+        let filter = match keep {
+            Some(k) => doc!{"account": account, "_id": {"$ne": k}},
+            None => doc!{"account": account}
+        };
+
+        let result = db.collection::<Session>("sessions").delete_many(filter).await?;
+        Ok(result.deleted_count)
+    }
+
+    pub async fn create_indexes(db: &Database) -> Result<(), AppError> {
+        let collection = db.collection::<Session>("sessions");
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"token_hash": 1})
+                .options(IndexOptions::builder().unique(true).build())
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"account": 1})
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"expires_at": 1})
+                .options(IndexOptions::builder().expire_after(Duration::from_secs(0)).build())
+                .build()
+        ).await?;
+
+        Ok(())
+    }
+
+    pub fn response(self, current: Option<ObjectId>) -> ResponseSession {
+        ResponseSession {
+            id: self._id.to_string(),
+            user_agent: self.user_agent,
+            ip: self.ip,
+            created_at: self.created_at.to_string(),
+            last_seen: self.last_seen.to_string(),
+            current: current == Some(self._id)
+        }
+    }
+}
+
+fn expiration_from(start: DateTime) -> DateTime {
+    let length_ms = SESSION_LENGTH_DAYS * 24 * 60 * 60 * 1000;
+    DateTime::from_millis(start.timestamp_millis() + length_ms as i64)
+}
+
+fn needs_touch(last_seen: DateTime, now: DateTime) -> bool {
+    now.timestamp_millis() - last_seen.timestamp_millis() >= LAST_SEEN_INTERVAL_MS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //expiration_from
+    #[test]
+    fn expires_after_session_length() {
+        let start = DateTime::from_millis(0);
+        let result = expiration_from(start);
+
+        assert_eq!(result.timestamp_millis(), 30 * 24 * 60 * 60 * 1000);
+    }
+
+    //needs_touch
+    #[test]
+    fn recent_session_not_touched() {
+        let now = DateTime::from_millis(LAST_SEEN_INTERVAL_MS);
+        let last_seen = DateTime::from_millis(1);
+
+        assert!(!needs_touch(last_seen, now));
+    }
+
+    #[test]
+    fn stale_session_touched() {
+        let now = DateTime::from_millis(LAST_SEEN_INTERVAL_MS);
+        let last_seen = DateTime::from_millis(0);
+
+        assert!(needs_touch(last_seen, now));
+    }
+
+    //response
+    #[test]
+    fn marks_current_session() {
+        let now = DateTime::now();
+        let session = Session {
+            _id: ObjectId::new(),
+            kind: SessionKind::Vendor,
+            account: ObjectId::new(),
+            token_hash: String::from("hash"),
+            user_agent: None,
+            ip: None,
+            created_at: now,
+            last_seen: now,
+            expires_at: now
+        };
+        let id = session._id;
+
+        let result = session.response(Some(id));
+        assert!(result.current);
+    }
+}
@@ -0,0 +1,235 @@
+use serde::{Serialize, Deserialize};
+use mongodb::{
+    bson::{self, oid::ObjectId, DateTime, doc},
+    options::IndexOptions,
+    Database,
+    IndexModel
+};
+use futures::stream::TryStreamExt;
+use crate::{
+    app_error::AppError,
+    models::token::{Token, TokenPurpose},
+    emails::{send_email_with_retry, send_in_background, staff_invite},
+    helpers::{email_collation, drop_uncollated_email_index}
+};
+
+/// What a logged in user can do within a vendor. Ordered from least to most
+/// access so roles can be compared with `>=`.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Inventory,
+    Manager,
+    Owner
+}
+
+/// Employee login belonging to a vendor. The vendor account itself is always
+/// the owner, so staff can only be managers or inventory.
+#[derive(Serialize, Deserialize)]
+pub struct Staff {
+    pub _id: ObjectId,
+    pub vendor: ObjectId,
+    pub email: String,
+    pub name: String,
+    pub role: Role,
+    pub pass_hash: Option<String>,
+    pub created_at: DateTime
+}
+
+#[derive(Serialize)]
+pub struct ResponseStaff {
+    id: String,
+    email: String,
+    name: String,
+    role: Role,
+    password_set: bool,
+    created_at: String
+}
+
+impl Staff {
+    pub fn new(vendor: ObjectId, email: String, name: String, role: Role) -> Result<Staff, AppError> {
+        validate_role(role)?;
+        if name.trim().is_empty() {
+            return Err(AppError::invalid_input("Name is required"));
+        }
+
+        Ok(Staff {
+            _id: ObjectId::new(),
+            vendor,
+            email,
+            name: name.trim().to_string(),
+            role,
+            pass_hash: None,
+            created_at: DateTime::now()
+        })
+    }
+
+    pub async fn insert(&self, db: &Database) -> Result<(), AppError> {
+        match db.collection::<Staff>("staff").insert_one(self).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(AppError::from_write(e, "A staff member with this email already exists"))
+        }
+    }
+
+    pub async fn find_by_id(db: &Database, staff_id: ObjectId) -> Result<Staff, AppError> {
+        match db.collection::<Staff>("staff").find_one(doc!{"_id": staff_id}).await {
+            Ok(Some(s)) => Ok(s),
+            Ok(None) => Err(AppError::not_found("Staff member with this ID does not exist")),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    pub async fn find_for_vendor(db: &Database, staff_id: ObjectId, vendor: ObjectId) -> Result<Staff, AppError> {
+        match db.collection::<Staff>("staff").find_one(doc!{"_id": staff_id, "vendor": vendor}).await {
+            Ok(Some(s)) => Ok(s),
+            Ok(None) => Err(AppError::not_found("Staff member with this ID does not exist")),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    /// Case insensitive, like the vendor lookup.
+    pub async fn find_by_email(db: &Database, email: &str) -> Result<Staff, AppError> {
+        let result = db.collection::<Staff>("staff")
+            .find_one(doc!{"email": email.trim().to_lowercase()})
+            .collation(email_collation())
+            .await;
+
+        match result {
+            Ok(Some(s)) => Ok(s),
+            Ok(None) => Err(AppError::not_found("Staff member with this email does not exist")),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    pub async fn find_by_vendor(db: &Database, vendor: ObjectId) -> Result<Vec<Staff>, AppError> {
+        let cursor = db.collection::<Staff>("staff")
+            .find(doc!{"vendor": vendor})
+            .sort(doc!{"created_at": 1})
+            .await?;
+
+        let staff: Vec<Staff> = cursor.try_collect().await?;
+        Ok(staff)
+    }
+
+    /// Issues a new invite token, invalidating any earlier one, and emails it
+    /// to the staff member.
+    pub async fn send_invite(&self, db: &Database, store: &str) -> Result<(), AppError> {
+        let html = self.issue_invite(db, store).await?;
+        send_email_with_retry(&self.email, &self.name, &invite_subject(store), html).await
+    }
+
+    /// Like `send_invite`, but only the token is issued before returning. Used
+    /// right after the staff member is inserted, where a mail failure
+    /// shouldn't fail the request. The invite can be resent if it never
+    /// arrives.
+    pub async fn send_invite_in_background(&self, db: &Database, store: &str) -> Result<(), AppError> {
+        let html = self.issue_invite(db, store).await?;
+        let (email, name, subject) = (self.email.clone(), self.name.clone(), invite_subject(store));
+        send_in_background("staff invite", async move {
+            send_email_with_retry(&email, &name, &subject, html).await
+        });
+        Ok(())
+    }
+
+    /// Issues the invite token and returns the email body carrying it.
+    async fn issue_invite(&self, db: &Database, store: &str) -> Result<String, AppError> {
+        if self.pass_hash.is_some() {
+            return Err(AppError::invalid_input("Staff member has already created a password"));
+        }
+
+        let token = Token::issue(db, self._id, TokenPurpose::StaffInvite).await?;
+        Ok(staff_invite(self.name.clone(), store.to_string(), self._id.to_string(), token))
+    }
+
+    pub async fn set_role(&self, db: &Database, role: Role) -> Result<(), AppError> {
+        validate_role(role)?;
+        let role = bson::to_bson(&role).map_err(|_| AppError::InternalError)?;
+        db.collection::<Staff>("staff")
+            .update_one(doc!{"_id": self._id}, doc!{"$set": {"role": role}})
+            .await?;
+        Ok(())
+    }
+
+    pub async fn set_pass_hash(&self, db: &Database, pass_hash: String) -> Result<(), AppError> {
+        db.collection::<Staff>("staff")
+            .update_one(doc!{"_id": self._id}, doc!{"$set": {"pass_hash": pass_hash}})
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete(&self, db: &Database) -> Result<(), AppError> {
+        db.collection::<Staff>("staff").delete_one(doc!{"_id": self._id}).await?;
+        Ok(())
+    }
+
+    /// The email index was first built without a collation.
+    pub async fn migrate(db: &Database) -> Result<(), AppError> {
+        drop_uncollated_email_index(&db.collection::<Staff>("staff")).await
+    }
+
+    pub async fn create_indexes(db: &Database) -> Result<(), AppError> {
+        let collection = db.collection::<Staff>("staff");
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"email": 1})
+                .options(IndexOptions::builder().unique(true).collation(email_collation()).build())
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"vendor": 1})
+                .build()
+        ).await?;
+
+        Ok(())
+    }
+
+    pub fn response(self) -> ResponseStaff {
+        ResponseStaff {
+            id: self._id.to_string(),
+            email: self.email,
+            name: self.name,
+            role: self.role,
+            password_set: self.pass_hash.is_some(),
+            created_at: self.created_at.to_string()
+        }
+    }
+}
+
+fn invite_subject(store: &str) -> String {
+    format!("You've been invited to {} on Inlet.Shop", store)
+}
+
+fn validate_role(role: Role) -> Result<(), AppError> {
+    match role {
+        Role::Owner => Err(AppError::invalid_input("Staff cannot have the owner role")),
+        _ => Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Role
+    #[test]
+    fn roles_are_ordered() {
+        assert!(Role::Owner > Role::Manager);
+        assert!(Role::Manager > Role::Inventory);
+    }
+
+    //validate_role
+    #[test]
+    fn rejects_owner_role() {
+        let result = Staff::new(ObjectId::new(), String::from("jane@shop.com"), String::from("Jane"), Role::Owner);
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn accepts_staff_role() {
+        let result = Staff::new(ObjectId::new(), String::from("jane@shop.com"), String::from("Jane"), Role::Inventory);
+        assert!(result.is_ok());
+    }
+}
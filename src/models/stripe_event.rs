@@ -0,0 +1,59 @@
+use serde::{Serialize, Deserialize};
+use mongodb::{
+    bson::{DateTime, doc},
+    options::IndexOptions,
+    Database,
+    IndexModel
+};
+use std::time::Duration;
+use crate::app_error::AppError;
+
+/// Stripe retries deliveries for up to three days, keep ids a while longer.
+const KEEP_FOR: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Webhook event that has been handled, keyed by the Stripe event id so
+/// redeliveries are ignored.
+#[derive(Serialize, Deserialize)]
+pub struct StripeEvent {
+    pub _id: String,
+    pub kind: String,
+    pub created_at: DateTime
+}
+
+impl StripeEvent {
+    /// Records the event. Returns false if it was already recorded.
+    pub async fn claim(db: &Database, id: &str, kind: &str) -> Result<bool, AppError> {
+        let event = StripeEvent {
+            _id: id.to_string(),
+            kind: kind.to_string(),
+            created_at: DateTime::now()
+        };
+
+        match db.collection::<StripeEvent>("stripe_events").insert_one(&event).await {
+            Ok(_) => Ok(true),
+            Err(e) => match AppError::from_write(e, "Event already handled") {
+                AppError::Conflict(_) => Ok(false),
+                e => Err(e)
+            }
+        }
+    }
+
+    /// Forgets an event that failed so Stripe's retry is handled.
+    pub async fn release(db: &Database, id: &str) -> Result<(), AppError> {
+        db.collection::<StripeEvent>("stripe_events")
+            .delete_one(doc!{"_id": id})
+            .await?;
+        Ok(())
+    }
+
+    pub async fn create_indexes(db: &Database) -> Result<(), AppError> {
+        db.collection::<StripeEvent>("stripe_events").create_index(
+            IndexModel::builder()
+                .keys(doc!{"created_at": 1})
+                .options(IndexOptions::builder().expire_after(KEEP_FOR).build())
+                .build()
+        ).await?;
+
+        Ok(())
+    }
+}
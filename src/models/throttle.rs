@@ -0,0 +1,237 @@
+use serde::{Serialize, Deserialize};
+use mongodb::{
+    bson::{DateTime, doc},
+    options::{IndexOptions, ReturnDocument},
+    Database,
+    IndexModel
+};
+use futures::stream::TryStreamExt;
+use std::time::Duration;
+use actix_web::ResponseError;
+use crate::app_error::AppError;
+
+const RECORD_LIFETIME_SECS: u64 = 24 * 60 * 60;
+
+pub struct ThrottlePolicy {
+    pub max_failures: i32,
+    pub base_lockout_secs: u64,
+    pub max_lockout_secs: u64
+}
+
+pub const ACCOUNT_POLICY: ThrottlePolicy = ThrottlePolicy {
+    max_failures: 5,
+    base_lockout_secs: 30,
+    max_lockout_secs: 60 * 60
+};
+
+pub const IP_POLICY: ThrottlePolicy = ThrottlePolicy {
+    max_failures: 20,
+    base_lockout_secs: 60,
+    max_lockout_secs: 60 * 60
+};
+
+#[derive(Serialize, Deserialize)]
+pub struct Throttle {
+    pub key: String,
+    pub failures: i32,
+    pub locked_until: Option<DateTime>,
+    pub updated_at: DateTime
+}
+
+impl Throttle {
+    /// Fails with `AppError::TooManyRequests` if any of the keys is locked.
+    async fn check(db: &Database, keys: &[&str]) -> Result<(), AppError> {
+        let now = DateTime::now();
+        let cursor = db.collection::<Throttle>("throttles")
+            .find(doc!{"key": {"$in": keys}, "locked_until": {"$gt": now}})
+            .await?;
+        let locked: Vec<Throttle> = cursor.try_collect().await?;
+
+        let retry_after = locked.iter()
+            .filter_map(|t| t.locked_until)
+            .map(|until| seconds_between(now, until))
+            .max();
+
+        match retry_after {
+            Some(secs) => Err(AppError::TooManyRequests(secs)),
+            None => Ok(())
+        }
+    }
+
+    async fn record_failure(
+        db: &Database,
+        key: &str,
+        policy: &ThrottlePolicy
+    ) -> Result<(), AppError> {
+        let collection = db.collection::<Throttle>("throttles");
+        let now = DateTime::now();
+
+        let throttle = collection.find_one_and_update(
+            doc!{"key": key},
+            doc!{
+                "$inc": {"failures": 1},
+                "$set": {"updated_at": now},
+                "$setOnInsert": {"locked_until": null}
+            }
+        )
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .await?
+            .ok_or(AppError::InternalError)?;
+
+        if let Some(secs) = lockout_secs(policy, throttle.failures) {
+            let until = DateTime::from_millis(now.timestamp_millis() + (secs * 1000) as i64);
+            collection.update_one(doc!{"key": key}, doc!{"$set": {"locked_until": until}}).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn clear(db: &Database, key: &str) -> Result<(), AppError> {
+        db.collection::<Throttle>("throttles").delete_one(doc!{"key": key}).await?;
+        Ok(())
+    }
+
+    pub async fn create_indexes(db: &Database) -> Result<(), AppError> {
+        let collection = db.collection::<Throttle>("throttles");
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"key": 1})
+                .options(IndexOptions::builder().unique(true).build())
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"updated_at": 1})
+                .options(IndexOptions::builder()
+                    .expire_after(Duration::from_secs(RECORD_LIFETIME_SECS))
+                    .build())
+                .build()
+        ).await?;
+
+        Ok(())
+    }
+}
+
+/// Account and IP limits for attempts at one action. Create it with
+/// `guard` before doing any work, then report how the attempt went.
+pub struct Guard<'a> {
+    db: &'a Database,
+    account_key: Option<String>,
+    ip_key: String
+}
+
+/// Fails with `AppError::TooManyRequests` if the account or IP is locked out
+/// of the action.
+pub async fn guard<'a>(
+    db: &'a Database,
+    action: &str,
+    account: Option<&str>,
+    ip: Option<String>
+) -> Result<Guard<'a>, AppError> {
+    let guard = Guard {
+        db,
+        account_key: account.map(|a| account_key(action, a)),
+        ip_key: ip_key(action, ip)
+    };
+
+    let mut keys = vec![guard.ip_key.as_str()];
+    keys.extend(guard.account_key.as_deref());
+    Throttle::check(db, &keys).await?;
+
+    Ok(guard)
+}
+
+impl Guard<'_> {
+    /// Counts the attempt whatever the outcome. Used where the response must
+    /// look the same whether or not the account exists, and where each
+    /// request is the expensive part (sending email, holding stock).
+    pub async fn count(&self) -> Result<(), AppError> {
+        if let Some(key) = &self.account_key {
+            Throttle::record_failure(self.db, key, &ACCOUNT_POLICY).await?;
+        }
+        Throttle::record_failure(self.db, &self.ip_key, &IP_POLICY).await
+    }
+
+    /// Counts a failed attempt and clears the account's failures on success.
+    /// Server errors aren't the caller's fault so they don't count.
+    pub async fn settle<T>(&self, result: Result<T, AppError>) -> Result<T, AppError> {
+        match result {
+            Ok(value) => {
+                if let Some(key) = &self.account_key {
+                    Throttle::clear(self.db, key).await?;
+                }
+                Ok(value)
+            },
+            Err(e) if e.status_code().is_server_error() => Err(e),
+            Err(e) => {
+                self.count().await?;
+                Err(e)
+            }
+        }
+    }
+}
+
+pub fn account_key(action: &str, account: &str) -> String {
+    format!("{}:account:{}", action, account.trim().to_lowercase())
+}
+
+pub fn ip_key(action: &str, ip: Option<String>) -> String {
+    format!("{}:ip:{}", action, ip.unwrap_or_else(|| String::from("unknown")))
+}
+
+fn lockout_secs(policy: &ThrottlePolicy, failures: i32) -> Option<u64> {
+    if failures < policy.max_failures {
+        return None;
+    }
+
+    let exponent = (failures - policy.max_failures).min(16) as u32;
+    let secs = policy.base_lockout_secs.saturating_mul(1 << exponent);
+    Some(secs.min(policy.max_lockout_secs))
+}
+
+fn seconds_between(start: DateTime, end: DateTime) -> u64 {
+    let millis = (end.timestamp_millis() - start.timestamp_millis()).max(0) as u64;
+    millis.div_ceil(1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //lockout_secs
+    #[test]
+    fn no_lockout_below_limit() {
+        let result = lockout_secs(&ACCOUNT_POLICY, 4);
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn lockout_doubles() {
+        assert_eq!(lockout_secs(&ACCOUNT_POLICY, 5), Some(30));
+        assert_eq!(lockout_secs(&ACCOUNT_POLICY, 6), Some(60));
+        assert_eq!(lockout_secs(&ACCOUNT_POLICY, 7), Some(120));
+    }
+
+    #[test]
+    fn lockout_is_capped() {
+        let result = lockout_secs(&ACCOUNT_POLICY, 1000);
+        assert_eq!(result, Some(60 * 60));
+    }
+
+    //seconds_between
+    #[test]
+    fn rounds_seconds_up() {
+        let result = seconds_between(DateTime::from_millis(0), DateTime::from_millis(1001));
+        assert_eq!(result, 2);
+    }
+
+    //account_key
+    #[test]
+    fn account_key_normalized() {
+        let result = account_key("login", " Jane@Shop.com ");
+        assert_eq!(result, "login:account:jane@shop.com");
+    }
+}
@@ -0,0 +1,195 @@
+use serde::{Serialize, Deserialize};
+use mongodb::{
+    bson::{self, oid::ObjectId, DateTime, Document, doc},
+    options::IndexOptions,
+    Database,
+    IndexModel
+};
+use futures::stream::TryStreamExt;
+use std::time::Duration;
+use crate::{
+    app_error::AppError,
+    helpers::{generate_token, hash_token}
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenPurpose {
+    Invite,
+    Reset,
+    EmailChange,
+    LoginLink,
+    StaffInvite,
+    CustomerReset,
+    StaffReset
+}
+
+impl TokenPurpose {
+    fn lifetime_ms(&self) -> i64 {
+        match self {
+            TokenPurpose::Invite => 7 * 24 * 60 * 60 * 1000,
+            TokenPurpose::Reset => 60 * 60 * 1000,
+            TokenPurpose::EmailChange => 24 * 60 * 60 * 1000,
+            TokenPurpose::LoginLink => 15 * 60 * 1000,
+            TokenPurpose::StaffInvite => 7 * 24 * 60 * 60 * 1000,
+            TokenPurpose::CustomerReset => 60 * 60 * 1000,
+            TokenPurpose::StaffReset => 60 * 60 * 1000
+        }
+    }
+}
+
+/// Single use token sent by email. Only the hash is stored and the token is
+/// deleted as soon as it is used. `account` is the vendor, staff member or
+/// customer the token was issued to, depending on the purpose.
+#[derive(Serialize, Deserialize)]
+pub struct Token {
+    pub _id: ObjectId,
+    pub account: ObjectId,
+    pub purpose: TokenPurpose,
+    pub token_hash: String,
+    pub new_email: Option<String>,
+    pub created_at: DateTime,
+    pub expires_at: DateTime
+}
+
+impl Token {
+    /// Creates a new token, replacing any outstanding token for the same
+    /// account and purpose. Returns the raw token to send by email.
+    pub async fn issue(
+        db: &Database,
+        account: ObjectId,
+        purpose: TokenPurpose
+    ) -> Result<String, AppError> {
+        Token::issue_with_email(db, account, purpose, None).await
+    }
+
+    /// Same as `issue`, but stores the address an email change token is
+    /// confirming.
+    pub async fn issue_with_email(
+        db: &Database,
+        account: ObjectId,
+        purpose: TokenPurpose,
+        new_email: Option<String>
+    ) -> Result<String, AppError> {
+        let collection = db.collection::<Token>("tokens");
+        let raw = generate_token();
+        let now = DateTime::now();
+        let token = Token {
+            _id: ObjectId::new(),
+            account,
+            purpose,
+            token_hash: hash_token(&raw),
+            new_email,
+            created_at: now,
+            expires_at: DateTime::from_millis(now.timestamp_millis() + purpose.lifetime_ms())
+        };
+
+        let purpose_bson = bson::to_bson(&purpose).map_err(|_| AppError::InternalError)?;
+        collection.delete_many(doc!{"account": account, "purpose": purpose_bson}).await?;
+        collection.insert_one(&token).await?;
+        Ok(raw)
+    }
+
+    /// Validates and deletes the token in one operation so that it can only
+    /// ever be used once.
+    pub async fn consume(
+        db: &Database,
+        account: ObjectId,
+        purpose: TokenPurpose,
+        raw: &str
+    ) -> Result<Token, AppError> {
+        let filter = doc!{
+            "account": account,
+            "purpose": bson::to_bson(&purpose).map_err(|_| AppError::InternalError)?,
+            "token_hash": hash_token(raw),
+            "expires_at": {"$gt": DateTime::now()}
+        };
+
+        match db.collection::<Token>("tokens").find_one_and_delete(filter).await {
+            Ok(Some(t)) => Ok(t),
+            Ok(None) => Err(AppError::forbidden("Invalid or expired token")),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    /// Brings data from before the tokens collection up to date. Safe to run
+    /// on every start.
+    ///
+    /// Tokens used to store the account in `vendor`. Invites used to be a
+    /// UUID on the vendor document; outstanding ones are moved here with a
+    /// fresh expiry so links already sent keep working. Old reset tokens are
+    /// dropped, those vendors can request a new reset email.
+    pub async fn migrate(db: &Database) -> Result<(), AppError> {
+        let collection = db.collection::<Token>("tokens");
+        collection.update_many(
+            doc!{"vendor": {"$exists": true}},
+            doc!{"$rename": {"vendor": "account"}}
+        ).await?;
+        //Fails once the old index is gone
+        let _ = collection.drop_index("vendor_1_purpose_1").await;
+
+        let vendors = db.collection::<Document>("vendors");
+        let mut cursor = vendors
+            .find(doc!{"token": {"$type": "string"}, "pass_hash": null})
+            .projection(doc!{"token": 1})
+            .await?;
+        let now = DateTime::now();
+        while let Some(vendor) = cursor.try_next().await? {
+            let (id, raw) = match (vendor.get_object_id("_id"), vendor.get_str("token")) {
+                (Ok(id), Ok(raw)) => (id, raw),
+                _ => continue
+            };
+            let token = Token {
+                _id: ObjectId::new(),
+                account: id,
+                purpose: TokenPurpose::Invite,
+                token_hash: hash_token(raw),
+                new_email: None,
+                created_at: now,
+                expires_at: DateTime::from_millis(now.timestamp_millis() + TokenPurpose::Invite.lifetime_ms())
+            };
+            collection.insert_one(&token).await?;
+            vendors.update_one(doc!{"_id": id}, doc!{"$unset": {"token": ""}}).await?;
+        }
+
+        vendors.update_many(doc!{"token": {"$exists": true}}, doc!{"$unset": {"token": ""}}).await?;
+        Ok(())
+    }
+
+    pub async fn create_indexes(db: &Database) -> Result<(), AppError> {
+        let collection = db.collection::<Token>("tokens");
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"token_hash": 1})
+                .options(IndexOptions::builder().unique(true).build())
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"account": 1, "purpose": 1})
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"expires_at": 1})
+                .options(IndexOptions::builder().expire_after(Duration::from_secs(0)).build())
+                .build()
+        ).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //lifetime_ms
+    #[test]
+    fn reset_is_short_lived() {
+        assert!(TokenPurpose::Reset.lifetime_ms() < TokenPurpose::Invite.lifetime_ms());
+    }
+}
@@ -1,10 +1,25 @@
 use serde::{Serialize, Deserialize};
 use mongodb::{
-    bson::{oid::ObjectId, DateTime, Document, doc},
-    Database
+    bson::{oid::ObjectId, Bson, DateTime, Document, doc},
+    options::IndexOptions,
+    Database,
+    IndexModel
 };
 use futures::stream::TryStreamExt;
-use crate::app_error::AppError;
+use crate::{
+    app_error::AppError,
+    models::{
+        session::Session,
+        token::{Token, TokenPurpose},
+        audit::{self, Actor, AuditEntry}
+    },
+    helpers::{slugify, normalize_email, email_collation},
+    emails::{send_email_with_retry, send_in_background, invite},
+    dto::vendor::NewVendor
+};
+
+const DUPLICATE_MESSAGE: &str = "A vendor with this email or URL already exists";
+const INVITE_SUBJECT: &str = "Welcome to Inlet.Shop";
 
 #[derive(Serialize, Deserialize)]
 pub struct Vendor {
@@ -14,12 +29,13 @@ pub struct Vendor {
     pub store: String,
     pub url: String,
     pub pass_hash: Option<String>,
-    pub token: String,
     pub public_data: PublicData,
     pub html: Option<String>,
     pub active: bool,
+    pub suspension: Option<Suspension>,
     pub new_order_send_email: bool,
     pub stripe: Option<StripeData>,
+    pub two_factor: Option<TwoFactor>,
     pub created_at: DateTime
 }
 
@@ -42,6 +58,28 @@ pub struct StripeData {
     pub activated: bool
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct Suspension {
+    pub reason: String,
+    pub suspended_at: DateTime
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TwoFactor {
+    pub secret: String,
+    pub enabled: bool,
+    pub recovery_codes: Vec<String>,
+    pub last_step: Option<i64>
+}
+
+/// A two-factor code that passed verification and still has to be marked
+/// as used.
+#[derive(Debug, PartialEq)]
+pub enum TwoFactorUse {
+    Step(i64),
+    RecoveryCode(String)
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Address {
     pub text: Option<String>,
@@ -74,10 +112,174 @@ pub struct ResponseVendor {
     url: String,
     public_data: PublicData,
     html: Option<String>,
-    new_order_send_email: bool
+    new_order_send_email: bool,
+    two_factor_enabled: bool
+}
+
+#[derive(Serialize)]
+pub struct ResponseAdminVendor {
+    id: String,
+    email: String,
+    owner: String,
+    store: String,
+    url: String,
+    active: bool,
+    suspension_reason: Option<String>,
+    password_set: bool,
+    stripe_account: Option<String>,
+    stripe_activated: bool,
+    created_at: String
 }
 
 impl Vendor {
+    pub fn new(email: String, owner: String, store: String, url: String) -> Vendor {
+        Vendor {
+            _id: ObjectId::new(),
+            email,
+            owner,
+            store,
+            url,
+            pass_hash: None,
+            public_data: PublicData {
+                phone: None,
+                email: None,
+                address: None,
+                slogan: None,
+                description: None,
+                image: None,
+                hours: None,
+                links: None,
+                website: None
+            },
+            html: None,
+            active: true,
+            suspension: None,
+            new_order_send_email: true,
+            stripe: None,
+            two_factor: None,
+            created_at: DateTime::now()
+        }
+    }
+
+    /// Creates a vendor without a password for the admin and operator
+    /// routes, emails them an invite to create one and records who did it.
+    pub async fn onboard(
+        db: &Database,
+        new: &NewVendor,
+        actor: Actor,
+        ip: Option<String>
+    ) -> Result<Vendor, AppError> {
+        validate_onboarding(&new.owner, &new.store)?;
+
+        let email = normalize_email(&new.email)?;
+        if Vendor::find_by_email(db, &email).await.is_ok() {
+            return Err(AppError::conflict(DUPLICATE_MESSAGE));
+        }
+
+        let url = Vendor::unique_url(db, new.url.as_deref().unwrap_or(&new.store)).await?;
+        let vendor = Vendor::new(email, new.owner.trim().to_string(), new.store.trim().to_string(), url);
+        vendor.insert(db).await?;
+        AuditEntry::record(
+            db,
+            actor,
+            Some(vendor._id),
+            "vendor.create",
+            Some(vendor._id),
+            ip,
+            audit::diff(&Document::new(), &audit::to_document(&vendor))
+        ).await;
+
+        //The vendor exists now, so a mail failure shouldn't fail the request
+        //and leave a retry to hit the duplicate email. The invite can be
+        //resent if it never arrives.
+        let html = vendor.issue_invite(db).await?;
+        let (email, owner) = (vendor.email.clone(), vendor.owner.clone());
+        send_in_background("vendor invite", async move {
+            send_email_with_retry(&email, &owner, INVITE_SUBJECT, html).await
+        });
+
+        Ok(vendor)
+    }
+
+    /// Issues a new invite token, invalidating any earlier one, and emails it
+    /// to the vendor.
+    pub async fn send_invite(&self, db: &Database) -> Result<(), AppError> {
+        let html = self.issue_invite(db).await?;
+        send_email_with_retry(&self.email, &self.owner, INVITE_SUBJECT, html).await
+    }
+
+    /// Issues the invite token and returns the email body carrying it.
+    async fn issue_invite(&self, db: &Database) -> Result<String, AppError> {
+        if self.pass_hash.is_some() {
+            return Err(AppError::invalid_input("Vendor has already created a password"));
+        }
+
+        let token = Token::issue(db, self._id, TokenPurpose::Invite).await?;
+        Ok(invite(self.owner.clone(), self.store.clone(), self._id.to_string(), token))
+    }
+
+    /// Resends the invite for the admin and operator routes and records who
+    /// did it.
+    pub async fn resend_invite(
+        db: &Database,
+        vendor_id: ObjectId,
+        actor: Actor,
+        ip: Option<String>
+    ) -> Result<(), AppError> {
+        Vendor::find_by_id(db, vendor_id).await?.send_invite(db).await?;
+        AuditEntry::record(db, actor, Some(vendor_id), "vendor.invite", Some(vendor_id), ip, Document::new()).await;
+        Ok(())
+    }
+
+    pub async fn insert(&self, db: &Database) -> Result<(), AppError> {
+        match db.collection::<Vendor>("vendors").insert_one(self).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(AppError::from_write(e, DUPLICATE_MESSAGE))
+        }
+    }
+
+    pub async fn search(
+        db: &Database,
+        filter: Document,
+        page: u64,
+        results: u64
+    ) -> Result<Vec<Vendor>, AppError> {
+        let cursor = db.collection::<Vendor>("vendors")
+            .find(filter)
+            .sort(doc!{"created_at": -1})
+            .skip(page * results)
+            .limit(results as i64)
+            .await?;
+
+        let vendors: Vec<Vendor> = cursor.try_collect().await?;
+        Ok(vendors)
+    }
+
+    pub async fn url_exists(db: &Database, url: &str) -> Result<bool, AppError> {
+        let count = db.collection::<Document>("vendors")
+            .count_documents(doc!{"url": url})
+            .await?;
+        Ok(count > 0)
+    }
+
+    /// Builds a URL slug from the text, adding a numbered suffix if the slug
+    /// is already taken by another vendor.
+    pub async fn unique_url(db: &Database, text: &str) -> Result<String, AppError> {
+        let base = slugify(text);
+        if base.is_empty() {
+            return Err(AppError::invalid_input("Store name must contain letters or numbers"));
+        }
+
+        let mut candidate = base.clone();
+        let mut suffix = 2;
+        while Vendor::url_exists(db, &candidate).await? {
+            candidate = format!("{}-{}", base, suffix);
+            suffix += 1;
+        }
+
+        Ok(candidate)
+    }
+
     pub async fn get_many(
         db: &Database,
         projection_doc: Document,
@@ -85,7 +287,7 @@ impl Vendor {
         results: i64
     ) -> Result<Vec<Document>, AppError> {
         let cursor = db.collection::<Document>("vendors")
-            .find(doc!{})
+            .find(doc!{"active": true})
             .skip(page)
             .limit(results)
             .projection(projection_doc)
@@ -99,30 +301,186 @@ impl Vendor {
         match db.collection::<Vendor>("vendors").find_one(doc!{"_id": vendor_id}).await {
             Ok(Some(v)) => Ok(v),
             Ok(None) => Err(AppError::not_found("Vendor with this ID not found")),
-            Err(e) => Err(AppError::Database(e.into()))
+            Err(e) => Err(AppError::Database(e))
         }
     }
 
-    pub async fn find_by_email(db: &Database, email: &String) -> Result<Vendor, AppError> {
-        match db.collection::<Vendor>("vendors").find_one(doc!{"email": email}).await {
+    /// Case insensitive, so addresses stored before emails were normalized
+    /// are still found.
+    pub async fn find_by_email(db: &Database, email: &str) -> Result<Vendor, AppError> {
+        let result = db.collection::<Vendor>("vendors")
+            .find_one(doc!{"email": email.trim().to_lowercase()})
+            .collation(email_collation())
+            .await;
+
+        match result {
             Ok(Some(v)) => Ok(v),
             Ok(None) => Err(AppError::not_found("Vendor with this email does not exist")),
-            Err(e) => Err(AppError::Database(e.into()))
+            Err(e) => Err(AppError::Database(e))
         }
     }
 
     pub async fn find_by_url(db: &Database, url: &String) -> Result<Vendor, AppError> {
-        match db.collection::<Vendor>("vendors").find_one(doc!{"url": url}).await {
+        match db.collection::<Vendor>("vendors").find_one(doc!{"url": url, "active": true}).await {
             Ok(Some(v)) => Ok(v),
             Ok(None) => Err(AppError::not_found("The page you are looking for doesn't exist")),
-            Err(e) => Err(AppError::Database(e.into()))
+            Err(e) => Err(AppError::Database(e))
         }
     }
 
+    pub async fn verify_active(db: &Database, vendor_id: ObjectId) -> Result<(), AppError> {
+        match db.collection::<Document>("vendors")
+            .find_one(doc!{"_id": vendor_id, "active": true})
+            .projection(doc!{"_id": 1})
+            .await {
+                Ok(Some(_)) => Ok(()),
+                Ok(None) => Err(AppError::not_found("The page you are looking for doesn't exist")),
+                Err(e) => Err(AppError::Database(e))
+            }
+    }
+
+    pub fn ensure_active(&self) -> Result<(), AppError> {
+        match self.active {
+            true => Ok(()),
+            false => Err(AppError::forbidden("Vendor account is suspended. Contact support@inletsites.dev for help"))
+        }
+    }
+
+    /// Suspends or reactivates the vendor. Suspending requires a reason and
+    /// revokes all of the vendor's sessions.
+    pub async fn set_active(
+        &self,
+        db: &Database,
+        active: bool,
+        reason: Option<&str>
+    ) -> Result<(), AppError> {
+        self.update(db, suspension_update(active, reason)?).await?;
+        if !active {
+            Session::delete_all(db, self._id, None).await?;
+        }
+        Ok(())
+    }
+
+    /// Suspends or reinstates the vendor for the admin and operator routes
+    /// and records who did it. Returns the updated vendor.
+    pub async fn change_active(
+        db: &Database,
+        vendor_id: ObjectId,
+        active: bool,
+        reason: Option<&str>,
+        actor: Actor,
+        ip: Option<String>
+    ) -> Result<Vendor, AppError> {
+        let vendor = Vendor::find_by_id(db, vendor_id).await?;
+        vendor.set_active(db, active, reason).await?;
+
+        let updated = Vendor::find_by_id(db, vendor_id).await?;
+        AuditEntry::record(
+            db,
+            actor,
+            Some(vendor_id),
+            "vendor.set_active",
+            Some(vendor_id),
+            ip,
+            audit::diff(&audit::to_document(&vendor), &audit::to_document(&updated))
+        ).await;
+        Ok(updated)
+    }
+
     pub async fn update(&self, db: &Database, data: Document) -> Result<Vendor, AppError> {
-        match db.collection::<Vendor>("vendors").find_one_and_update(doc!{"_id": self._id}, doc!{"$set": data}).await? {
-            Some(v) => Ok(v),
-            None => Err(AppError::not_found("User with this ID does not exist"))
+        match db.collection::<Vendor>("vendors").find_one_and_update(doc!{"_id": self._id}, doc!{"$set": data}).await {
+            Ok(Some(v)) => Ok(v),
+            Ok(None) => Err(AppError::not_found("User with this ID does not exist")),
+            Err(e) => Err(AppError::from_write(e, DUPLICATE_MESSAGE))
+        }
+    }
+
+    /// Marks a two-factor code as used. The write only matches while the step
+    /// is newer than the last one used or the recovery code is unspent, so of
+    /// two logins racing with the same code only one gets through.
+    pub async fn use_two_factor(&self, db: &Database, code: TwoFactorUse) -> Result<(), AppError> {
+        let (filter, update) = match code {
+            TwoFactorUse::Step(step) => (
+                doc!{
+                    "_id": self._id,
+                    "$or": [
+                        {"two_factor.last_step": null},
+                        {"two_factor.last_step": {"$lt": step}}
+                    ]
+                },
+                doc!{"$set": {"two_factor.last_step": step}}
+            ),
+            TwoFactorUse::RecoveryCode(hash) => (
+                doc!{"_id": self._id, "two_factor.recovery_codes": &hash},
+                doc!{"$pull": {"two_factor.recovery_codes": &hash}}
+            )
+        };
+
+        match db.collection::<Vendor>("vendors").update_one(filter, update).await {
+            Ok(r) if r.matched_count == 1 => Ok(()),
+            Ok(_) => Err(AppError::Auth),
+            Err(e) => Err(AppError::Database(e))
+        }
+    }
+
+    /// Sets `stripe.activated` for the vendor with the connected account.
+    /// Returns the vendor as it was before, or None if no vendor has it.
+    pub async fn set_stripe_activated(
+        db: &Database,
+        account_id: &str,
+        activated: bool
+    ) -> Result<Option<Vendor>, AppError> {
+        match db.collection::<Vendor>("vendors")
+            .find_one_and_update(
+                doc!{"stripe.account_id": account_id},
+                doc!{"$set": {"stripe.activated": activated}}
+            )
+            .await {
+                Ok(v) => Ok(v),
+                Err(e) => Err(AppError::Database(e))
+            }
+    }
+
+    pub async fn create_indexes(db: &Database) -> Result<(), AppError> {
+        let collection = db.collection::<Vendor>("vendors");
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"email": 1})
+                .options(IndexOptions::builder().unique(true).collation(email_collation()).build())
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"url": 1})
+                .options(IndexOptions::builder().unique(true).build())
+                .build()
+        ).await?;
+
+        collection.create_index(
+            IndexModel::builder()
+                .keys(doc!{"stripe.account_id": 1})
+                .options(IndexOptions::builder().sparse(true).build())
+                .build()
+        ).await?;
+
+        Ok(())
+    }
+
+    pub fn admin_response(self) -> ResponseAdminVendor {
+        ResponseAdminVendor {
+            id: self._id.to_string(),
+            email: self.email,
+            owner: self.owner,
+            store: self.store,
+            url: self.url,
+            active: self.active,
+            suspension_reason: self.suspension.map(|s| s.reason),
+            password_set: self.pass_hash.is_some(),
+            stripe_account: self.stripe.as_ref().map(|s| s.account_id.clone()),
+            stripe_activated: self.stripe.is_some_and(|s| s.activated),
+            created_at: self.created_at.to_string()
         }
     }
 
@@ -135,7 +493,89 @@ impl Vendor {
             url: self.url,
             public_data: self.public_data,
             html: self.html,
-            new_order_send_email: self.new_order_send_email
+            new_order_send_email: self.new_order_send_email,
+            two_factor_enabled: self.two_factor.is_some_and(|t| t.enabled)
+        }
+    }
+}
+
+fn validate_onboarding(owner: &str, store: &str) -> Result<(), AppError> {
+    if owner.trim().is_empty() {
+        return Err(AppError::invalid_input("Owner name is required"));
+    }
+
+    if store.trim().is_empty() {
+        return Err(AppError::invalid_input("Store name is required"));
+    }
+
+    Ok(())
+}
+
+fn suspension_update(active: bool, reason: Option<&str>) -> Result<Document, AppError> {
+    if active {
+        return Ok(doc!{
+            "active": true,
+            "suspension": Bson::Null
+        });
+    }
+
+    let reason = match reason.map(str::trim) {
+        Some(r) if !r.is_empty() => r,
+        _ => return Err(AppError::invalid_input("A reason is required to suspend a vendor"))
+    };
+
+    Ok(doc!{
+        "active": false,
+        "suspension": {
+            "reason": reason,
+            "suspended_at": DateTime::now()
         }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //validate_onboarding
+    #[test]
+    fn rejects_empty_owner() {
+        let result = validate_onboarding("", "Inlet Sites");
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn rejects_empty_store() {
+        let result = validate_onboarding("John Doe", "  ");
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn accepts_valid_onboarding() {
+        let result = validate_onboarding("John Doe", "Inlet Sites");
+        assert!(result.is_ok());
+    }
+
+    //suspension_update
+    #[test]
+    fn suspension_requires_reason() {
+        let result = suspension_update(false, Some("  "));
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn records_suspension_reason() {
+        let result = suspension_update(false, Some("Chargebacks")).unwrap();
+
+        assert!(!result.get_bool("active").unwrap());
+        assert_eq!(result.get_document("suspension").unwrap().get_str("reason").unwrap(), "Chargebacks");
+    }
+
+    #[test]
+    fn reactivation_clears_suspension() {
+        let result = suspension_update(true, None).unwrap();
+
+        assert!(result.get_bool("active").unwrap());
+        assert_eq!(result.get("suspension"), Some(&Bson::Null));
     }
 }
@@ -0,0 +1,22 @@
+use actix_web::web;
+use crate::controllers::admin::{
+    login,
+    logout,
+    me,
+
+    vendors,
+    products
+};
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(login::route);
+    cfg.service(logout::route);
+    cfg.service(me::route);
+
+    cfg.service(vendors::create::route);
+    cfg.service(vendors::get_many::route);
+    cfg.service(vendors::invite::route);
+    cfg.service(vendors::set_active::route);
+
+    cfg.service(products::set_active::route);
+}
@@ -1,3 +1,6 @@
 pub mod other;
 pub mod vendor;
 pub mod user;
+pub mod operator;
+pub mod admin;
+pub mod stripe;
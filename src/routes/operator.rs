@@ -0,0 +1,14 @@
+use actix_web::web;
+use crate::controllers::operator::{
+    admins,
+    audit,
+    vendors
+};
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(admins::create::route);
+    cfg.service(vendors::create::route);
+    cfg.service(vendors::invite::route);
+    cfg.service(vendors::set_active::route);
+    cfg.service(audit::route);
+}
@@ -0,0 +1,6 @@
+use actix_web::web;
+use crate::controllers::stripe::webhook;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(webhook::route);
+}
@@ -1,10 +1,31 @@
 use actix_web::web;
 use crate::controllers::user::{
+    register,
+    login,
+    logout,
+    me,
+    update,
+    password_email,
+    reset_password,
+    checkout,
+
     products,
+    reservations,
     vendors
 };
 
 pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(register::route);
+    cfg.service(login::route);
+    cfg.service(logout::route);
+    cfg.service(me::route);
+    cfg.service(update::route);
+    cfg.service(password_email::route);
+    cfg.service(reset_password::route);
+    cfg.service(checkout::route);
+    cfg.service(reservations::create::route);
+    cfg.service(reservations::delete::route);
+
     cfg.service(vendors::get_one::route);
     cfg.service(vendors::get_many::route);
     cfg.service(vendors::get_one::route);
@@ -11,8 +11,19 @@ use crate::controllers::vendor::{
     reset_password,
     create_connect,
     create_session,
+    invite,
+    email,
+    confirm_email,
+    audit,
 
-    products
+    api_keys,
+    login_link,
+    orders,
+    products,
+    refunds,
+    sessions,
+    staff,
+    two_factor
 };
 
 pub fn config(cfg: &mut web::ServiceConfig) {
@@ -27,6 +38,10 @@ pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(reset_password::route);
     cfg.service(create_connect::route);
     cfg.service(create_session::route);
+    cfg.service(invite::route);
+    cfg.service(email::route);
+    cfg.service(confirm_email::route);
+    cfg.service(audit::route);
 
     cfg.service(products::create::route);
     cfg.service(products::delete::route);
@@ -35,4 +50,38 @@ pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(products::add_images::route);
     cfg.service(products::remove_images::route);
     cfg.service(products::update::route);
+
+    cfg.service(orders::get_many::route);
+    cfg.service(orders::get_one::route);
+    cfg.service(orders::update_status::route);
+
+    cfg.service(refunds::create::route);
+    cfg.service(refunds::get_many::route);
+
+    cfg.service(login_link::request::route);
+    cfg.service(login_link::verify::route);
+
+    cfg.service(api_keys::create::route);
+    cfg.service(api_keys::get_many::route);
+    cfg.service(api_keys::delete::route);
+
+    cfg.service(sessions::get_many::route);
+    cfg.service(sessions::delete::route);
+    cfg.service(sessions::delete_all::route);
+
+    cfg.service(staff::me::route);
+    cfg.service(staff::login::route);
+    cfg.service(staff::create_password::route);
+    cfg.service(staff::password_email::route);
+    cfg.service(staff::reset_password::route);
+    cfg.service(staff::create::route);
+    cfg.service(staff::get_many::route);
+    cfg.service(staff::update::route);
+    cfg.service(staff::delete::route);
+    cfg.service(staff::invite::route);
+
+    cfg.service(two_factor::enroll::route);
+    cfg.service(two_factor::confirm::route);
+    cfg.service(two_factor::disable::route);
+    cfg.service(two_factor::login::route);
 }
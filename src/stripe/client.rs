@@ -0,0 +1,166 @@
+use std::sync::Arc;
+use serde_json::Value;
+use crate::app_error::AppError;
+use super::{Method, StripeRequest, Transport, HttpTransport};
+
+/// Shared Stripe API client, injected into routes with `web::Data`.
+pub struct StripeClient {
+    transport: Arc<dyn Transport>
+}
+
+impl StripeClient {
+    pub fn new(transport: Arc<dyn Transport>) -> Self {
+        StripeClient {transport}
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(Arc::new(HttpTransport::from_env()))
+    }
+
+    /// POSTs a create request. The idempotency key makes retries of the same
+    /// operation return the original object instead of creating another.
+    pub async fn create(
+        &self,
+        path: &str,
+        params: Vec<(String, String)>,
+        idempotency_key: &str,
+        account: Option<&str>
+    ) -> Result<Value, AppError> {
+        self.send(StripeRequest {
+            method: Method::Post,
+            path: path.to_string(),
+            params,
+            account: account.map(String::from),
+            idempotency_key: Some(idempotency_key.to_string())
+        }).await
+    }
+
+    pub async fn retrieve(&self, path: &str, account: Option<&str>) -> Result<Value, AppError> {
+        self.send(StripeRequest {
+            method: Method::Get,
+            path: path.to_string(),
+            params: Vec::new(),
+            account: account.map(String::from),
+            idempotency_key: None
+        }).await
+    }
+
+    async fn send(&self, request: StripeRequest) -> Result<Value, AppError> {
+        let path = request.path.clone();
+        let response = self.transport.send(request).await?;
+
+        match (200..300).contains(&response.status) {
+            true => Ok(response.body),
+            false => {
+                eprintln!("Stripe request to {} failed ({}): {}", path, response.status, response.body);
+                Err(stripe_error(response.status, &response.body))
+            }
+        }
+    }
+}
+
+/// Reads a string field from a Stripe object.
+pub fn response_str<'a>(data: &'a Value, key: &str) -> Result<&'a str, AppError> {
+    data[key].as_str().ok_or_else(|| AppError::StripeError(String::from("Unexpected response from Stripe")))
+}
+
+pub fn response_i64(data: &Value, key: &str) -> Result<i64, AppError> {
+    data[key].as_i64().ok_or_else(|| AppError::StripeError(String::from("Unexpected response from Stripe")))
+}
+
+/// Maps a Stripe error response to an error for the caller. Stripe's message
+/// is passed on when it describes a problem with the request, anything on the
+/// platform side gets a generic message.
+fn stripe_error(status: u16, body: &Value) -> AppError {
+    let error = &body["error"];
+    let message = error["message"].as_str().unwrap_or("Stripe rejected the request");
+
+    match (status, error["type"].as_str()) {
+        (_, Some("card_error")) => AppError::invalid_input(message),
+        (_, Some("idempotency_error")) | (409, _) => AppError::conflict(message),
+        (404, _) => AppError::not_found(message),
+        (400, _) | (402, _) => AppError::invalid_input(message),
+        (429, _) => AppError::StripeError(String::from("Stripe is busy, try again shortly")),
+        (401, _) | (403, _) => AppError::StripeError(String::from("Stripe rejected the platform credentials")),
+        _ => AppError::StripeError(String::from("Stripe is unavailable, try again shortly"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::stripe::fake::FakeTransport;
+
+    fn error_body(kind: &str, message: &str) -> Value {
+        json!({"error": {"type": kind, "message": message}})
+    }
+
+    //create
+    #[tokio::test]
+    async fn sends_idempotency_key_and_account() {
+        let fake = FakeTransport::new();
+        fake.respond(200, json!({"id": "re_123"}));
+        let stripe = StripeClient::new(fake.clone());
+
+        let data = stripe.create(
+            "/v1/refunds",
+            vec![(String::from("amount"), String::from("500"))],
+            "refund-1",
+            Some("acct_1")
+        ).await.unwrap();
+
+        assert_eq!(response_str(&data, "id").unwrap(), "re_123");
+        let request = &fake.requests()[0];
+        assert_eq!(request.method, Method::Post);
+        assert_eq!(request.idempotency_key.as_deref(), Some("refund-1"));
+        assert_eq!(request.account.as_deref(), Some("acct_1"));
+    }
+
+    #[tokio::test]
+    async fn passes_on_stripe_message() {
+        let fake = FakeTransport::new();
+        fake.respond(400, error_body("invalid_request_error", "Refund amount is greater than unrefunded amount"));
+        let stripe = StripeClient::new(fake);
+
+        let result = stripe.create("/v1/refunds", Vec::new(), "refund-1", None).await;
+        match result {
+            Err(AppError::InvalidInput(msg)) => assert_eq!(msg, "Refund amount is greater than unrefunded amount"),
+            _ => panic!("Expected invalid input")
+        }
+    }
+
+    //retrieve
+    #[tokio::test]
+    async fn retrieve_has_no_idempotency_key() {
+        let fake = FakeTransport::new();
+        fake.respond(200, json!({"id": "pi_123"}));
+        let stripe = StripeClient::new(fake.clone());
+
+        stripe.retrieve("/v1/payment_intents/pi_123", None).await.unwrap();
+        let request = &fake.requests()[0];
+        assert_eq!(request.method, Method::Get);
+        assert!(request.idempotency_key.is_none());
+    }
+
+    //response_str
+    #[test]
+    fn missing_field_is_stripe_error() {
+        assert!(matches!(response_str(&json!({}), "id"), Err(AppError::StripeError(_))));
+    }
+
+    //stripe_error
+    #[test]
+    fn maps_status_to_error() {
+        assert!(matches!(stripe_error(402, &error_body("card_error", "Card declined")), AppError::InvalidInput(_)));
+        assert!(matches!(stripe_error(404, &error_body("invalid_request_error", "No such payment_intent")), AppError::NotFound(_)));
+        assert!(matches!(stripe_error(400, &error_body("idempotency_error", "Keys reused")), AppError::Conflict(_)));
+        assert!(matches!(stripe_error(503, &serde_json::Value::Null), AppError::StripeError(_)));
+    }
+
+    #[test]
+    fn hides_platform_auth_errors() {
+        let result = stripe_error(401, &error_body("invalid_request_error", "Invalid API Key provided: sk_test_***"));
+        assert!(!result.to_string().contains("sk_test"));
+    }
+}
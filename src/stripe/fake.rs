@@ -0,0 +1,40 @@
+use std::{collections::VecDeque, sync::{Arc, Mutex}};
+use futures::future::BoxFuture;
+use serde_json::{Value, json};
+use crate::app_error::AppError;
+use super::{StripeRequest, StripeResponse, Transport};
+
+/// In-memory Stripe for tests. Responses are queued up front and every
+/// request is recorded so tests can check what would have been sent.
+pub struct FakeTransport {
+    responses: Mutex<VecDeque<StripeResponse>>,
+    requests: Mutex<Vec<StripeRequest>>
+}
+
+impl FakeTransport {
+    pub fn new() -> Arc<Self> {
+        Arc::new(FakeTransport {
+            responses: Mutex::new(VecDeque::new()),
+            requests: Mutex::new(Vec::new())
+        })
+    }
+
+    pub fn respond(&self, status: u16, body: Value) {
+        self.responses.lock().unwrap().push_back(StripeResponse {status, body});
+    }
+
+    pub fn requests(&self) -> Vec<StripeRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Transport for FakeTransport {
+    fn send(&self, request: StripeRequest) -> BoxFuture<'_, Result<StripeResponse, AppError>> {
+        self.requests.lock().unwrap().push(request);
+        let response = self.responses.lock().unwrap().pop_front().unwrap_or(StripeResponse {
+            status: 500,
+            body: json!({"error": {"type": "api_error", "message": "No response queued"}})
+        });
+        Box::pin(async move { Ok(response) })
+    }
+}
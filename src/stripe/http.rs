@@ -0,0 +1,52 @@
+use futures::future::BoxFuture;
+use reqwest::Client;
+use serde_json::Value;
+use crate::app_error::AppError;
+use super::{Method, StripeRequest, StripeResponse, Transport};
+
+const DEFAULT_API_BASE: &str = "https://api.stripe.com";
+
+pub struct HttpTransport {
+    client: Client,
+    base: String,
+    key: Option<String>
+}
+
+impl HttpTransport {
+    /// Reads the secret key from `STRIPE_INLETSITES_KEY`. Set
+    /// `STRIPE_API_BASE` to point at a local stripe-mock server.
+    pub fn from_env() -> Self {
+        let base = std::env::var("STRIPE_API_BASE").unwrap_or_else(|_| DEFAULT_API_BASE.to_string());
+
+        HttpTransport {
+            client: Client::new(),
+            base: base.trim_end_matches('/').to_string(),
+            key: std::env::var("STRIPE_INLETSITES_KEY").ok()
+        }
+    }
+}
+
+impl Transport for HttpTransport {
+    fn send(&self, request: StripeRequest) -> BoxFuture<'_, Result<StripeResponse, AppError>> {
+        Box::pin(async move {
+            let key = self.key.as_ref().ok_or(AppError::InternalError)?;
+            let url = format!("{}{}", self.base, request.path);
+
+            let mut builder = match request.method {
+                Method::Get => self.client.get(url).query(&request.params),
+                Method::Post => self.client.post(url).form(&request.params)
+            }.bearer_auth(key);
+            if let Some(account) = &request.account {
+                builder = builder.header("Stripe-Account", account);
+            }
+            if let Some(idempotency_key) = &request.idempotency_key {
+                builder = builder.header("Idempotency-Key", idempotency_key);
+            }
+
+            let response = builder.send().await?;
+            let status = response.status().as_u16();
+            let body: Value = response.json().await.unwrap_or(Value::Null);
+            Ok(StripeResponse {status, body})
+        })
+    }
+}
@@ -0,0 +1,38 @@
+use futures::future::BoxFuture;
+use serde_json::Value;
+use crate::app_error::AppError;
+
+mod client;
+mod http;
+#[cfg(test)]
+pub mod fake;
+
+pub use client::{StripeClient, response_str, response_i64};
+pub use http::HttpTransport;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Method {
+    Get,
+    Post
+}
+
+#[derive(Clone, Debug)]
+pub struct StripeRequest {
+    pub method: Method,
+    pub path: String,
+    pub params: Vec<(String, String)>,
+    /// Connected account to act as, sent in the `Stripe-Account` header
+    pub account: Option<String>,
+    pub idempotency_key: Option<String>
+}
+
+pub struct StripeResponse {
+    pub status: u16,
+    pub body: Value
+}
+
+/// Sends requests to Stripe. Errors are only for failing to reach Stripe at
+/// all, error responses come back as a normal `StripeResponse`.
+pub trait Transport: Send + Sync {
+    fn send(&self, request: StripeRequest) -> BoxFuture<'_, Result<StripeResponse, AppError>>;
+}